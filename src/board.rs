@@ -0,0 +1,962 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+
+use crate::budget::Budget;
+use crate::dlx;
+use crate::piece::{Cell, Piece};
+use crate::puzzle::Puzzle;
+use crate::symmetry::{self, Transform};
+use crate::Error;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Solver {
+    /// Dancing Links over the exact cover matrix.
+    Dlx,
+    /// Plain backtracking over the placements.
+    Dfs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Strategy {
+    /// Place the pieces in order, each one anywhere it fits.
+    Piece,
+    /// Cover the first empty cell with any unused piece.
+    Cell,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub solver: Solver,
+    pub strategy: Strategy,
+    // Cut off branches that leave unfillable empty regions.
+    pub prune: bool,
+    pub threads: usize,
+    // Stop after this many solutions.
+    pub limit: Option<usize>,
+    pub timeout: Option<Duration>,
+    // Report one solution per class of solutions that are rotations or
+    // reflections of each other.
+    pub distinct: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        return Options { solver: Solver::Dlx, strategy: Strategy::Piece, prune: false, threads: 1,
+            limit: None, timeout: None, distinct: false };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    // Every solution was found.
+    Complete,
+    // The caller's visitor asked to stop.
+    Stopped,
+    LimitReached,
+    TimedOut,
+}
+
+// One bit per board cell, bit `r * width + c`. The 7x7 board fits in 49 bits.
+pub type Mask = u64;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub piece: usize,
+    pub orientation: usize,
+    pub row: usize,
+    pub col: usize,
+    pub mask: Mask,
+}
+
+// A solution as the placements that make it up, plus the filled grid.
+#[derive(Clone)]
+pub struct Solution {
+    pub placements: Vec<Placement>,
+    pub grid: Vec<Vec<Cell>>,
+    // How many solutions it stands for: the distinct rotations and
+    // reflections of it with `Options::distinct`, otherwise 1.
+    pub class: usize,
+}
+
+// Receives the board at each solution; `Board::solution` builds the
+// structured value only if the caller wants it.
+type Visitor<'a> = dyn FnMut(&Board) -> ControlFlow<()> + 'a;
+
+#[derive(Clone, Copy)]
+enum Branch {
+    Place(Placement),
+    // Leave the first empty cell uncovered.
+    Skip,
+    // The k-th row of the first column Dancing Links branches on.
+    Row(usize),
+}
+
+#[derive(Clone)]
+pub struct Board {
+    pub pieces: Vec<Vec<Piece>>,
+    placements: Vec<Vec<Placement>>,
+    by_cell: Vec<Vec<Placement>>,
+    // The puzzle's cells with the targets marked, before any piece goes in.
+    layout: Vec<Vec<Cell>>,
+    // The covered cells, as (label, row, col).
+    pub targets: Vec<(String, usize, usize)>,
+    // Every labelled cell of the puzzle, targets or not.
+    pub(crate) labels: Vec<(String, usize, usize)>,
+    sizes: Vec<usize>,
+    cells: Mask,
+    col_first: Mask,
+    col_last: Mask,
+    occupied: Mask,
+    placed: Vec<Placement>,
+    pub calls: usize,
+    prune: bool,
+    pub pruned: usize,
+    budget: Budget,
+    // Solutions looked up instead of searched for, as an index into
+    // `placements` per piece.
+    known: Option<Vec<Vec<usize>>>,
+}
+
+impl Board {
+    // The pieces have to leave exactly the cells with the `targets` labels
+    // free.
+    pub fn new(puzzle: &Puzzle, targets: &[&str]) -> Result<Board, Error> {
+        let layout = puzzle.board.clone();
+        let width = layout.first().map_or(0, |r| r.len());
+        if width == 0 || layout.iter().any(|r| r.len() != width) {
+            return Err(Error::RaggedBoard);
+        }
+        if width * layout.len() > Mask::BITS as usize {
+            return Err(Error::BoardTooLarge(width * layout.len()));
+        }
+        // The searches keep the set of placed pieces in a u64.
+        if puzzle.pieces.len() > u64::BITS as usize {
+            return Err(Error::TooManyPieces(puzzle.pieces.len()));
+        }
+        let bit = |r: usize, c: usize| -> Mask { 1 << (r * width + c) };
+        let coords = || itertools::iproduct!(0..layout.len(), 0..width);
+        let pieces: Vec<Vec<Piece>> = puzzle.pieces.iter().map(Piece::generate_positions).collect();
+
+        let mut placements = vec![];
+        for (i, pos) in pieces.iter().enumerate() {
+            let mut res = vec![];
+            for (r, c) in coords() {
+                for (o, p) in pos.iter().enumerate() {
+                    let occ = p.fit(&layout, r, c);
+                    if occ.is_empty() {
+                        continue;
+                    }
+                    let mask = occ.iter().fold(0, |m, &(rr, cc)| m | bit(rr, cc));
+                    res.push(Placement { piece: i, orientation: o, row: r, col: c, mask });
+                }
+            }
+            placements.push(res);
+        }
+
+        // Indexed by the lowest cell a placement covers.
+        let mut by_cell = vec![vec![]; width * layout.len()];
+        for p in placements.iter().flatten() {
+            by_cell[p.mask.trailing_zeros() as usize].push(*p);
+        }
+        let sizes = pieces.iter().map(|p| p[0].size()).collect();
+        let cells = coords().fold(0, |m, (r, c)| m | bit(r, c));
+        let col_first = (0..layout.len()).fold(0, |m, r| m | bit(r, 0));
+        let col_last = col_first << (width - 1);
+
+        let mut res = Board { pieces, placements, by_cell, layout, targets: vec![],
+            labels: puzzle.labels.clone(), sizes, cells,
+            col_first, col_last, occupied: 0, placed: vec![], calls: 0, prune: false, pruned: 0,
+            budget: Budget::default(), known: None };
+        res.set_targets(puzzle, targets)?;
+        return Ok(res);
+    }
+
+    // Moves the targets, keeping the placements worked out by `new`.
+    // `puzzle` must be the one the board was made from.
+    pub fn set_targets(&mut self, puzzle: &Puzzle, targets: &[&str]) -> Result<(), Error> {
+        let mut layout = self.layout.clone();
+        for (_, r, c) in &self.targets {
+            layout[*r][*c] = Cell::Empty;
+        }
+        let mut covered: Vec<(String, usize, usize)> = vec![];
+        for &label in targets {
+            let (name, r, c) = puzzle.label(label)
+                .ok_or(Error::UnknownLabel(label.to_string()))?.clone();
+            if covered.iter().any(|t| t.0 == name) {
+                return Err(Error::LabelTwice(name));
+            }
+            if layout.get(r).and_then(|row| row.get(c)) != Some(&Cell::Empty) {
+                return Err(Error::BadLabel(name));
+            }
+            layout[r][c] = Cell::Target(name.clone());
+            covered.push((name, r, c));
+        }
+        self.occupied = self.coords()
+            .filter(|&(r, c)| layout[r][c] != Cell::Empty)
+            .fold(0, |m, (r, c)| m | self.bit(r, c));
+        self.layout = layout;
+        self.targets = covered;
+        self.known = None;
+        return Ok(());
+    }
+
+    // The board's cells with the targets, before any piece goes in.
+    pub fn layout(&self) -> &[Vec<Cell>] {
+        return &self.layout;
+    }
+
+    pub fn width(&self) -> usize {
+        return self.layout[0].len();
+    }
+
+    pub fn height(&self) -> usize {
+        return self.layout.len();
+    }
+
+    fn coords(&self) -> itertools::Product<std::ops::Range<usize>, std::ops::Range<usize>> {
+        return itertools::iproduct!(0..self.height(), 0..self.width());
+    }
+
+    pub(crate) fn bit(&self, r: usize, c: usize) -> Mask {
+        return 1 << (r * self.width() + c);
+    }
+
+    fn grid(&self) -> Vec<Vec<Cell>> {
+        let mut res = self.layout.clone();
+        for p in &self.placed {
+            for (r, c) in self.pieces[p.piece][p.orientation].filled() {
+                res[p.row + r][p.col + c] = Cell::Piece(p.piece);
+            }
+        }
+        return res;
+    }
+
+    // The grid with `placements` on it, which needn't cover the board.
+    pub fn fill(&self, placements: &[Placement]) -> Solution {
+        let mut b = self.clone();
+        b.placed = placements.to_vec();
+        return b.solution(1);
+    }
+
+    // The way to put `piece` on exactly the cells in `mask`, if any.
+    pub(crate) fn placement(&self, piece: usize, mask: Mask) -> Option<Placement> {
+        return self.placements[piece].iter().find(|p| p.mask == mask).copied();
+    }
+
+    // Where each piece of `s` is, as an index that `set_known` takes. The
+    // same for every choice of targets. None if `s` isn't from this board.
+    pub fn indices(&self, s: &Solution) -> Option<Vec<usize>> {
+        let mut res = vec![0; self.pieces.len()];
+        for p in &s.placements {
+            res[p.piece] = self.placements.get(p.piece)?.iter().position(|q| q.mask == p.mask)?;
+        }
+        return Some(res);
+    }
+
+    // Makes the searches go through `solutions` instead, which must be the
+    // solutions for the current targets. Moving the targets forgets them.
+    pub fn set_known(&mut self, solutions: Vec<Vec<usize>>) -> Result<(), Error> {
+        let fits = solutions.iter().all(|s| {
+            return s.len() == self.pieces.len()
+                && s.iter().enumerate().all(|(i, &k)| k < self.placements[i].len());
+        });
+        if !fits {
+            return Err(Error::BadDb("placement out of range".to_string()));
+        }
+        self.known = Some(solutions);
+        return Ok(());
+    }
+
+    fn replay(&mut self, f: &mut Visitor) -> ControlFlow<()> {
+        let known = self.known.take().unwrap_or_default();
+        let mut res = ControlFlow::Continue(());
+        for s in &known {
+            self.placed = s.iter().enumerate().map(|(i, &k)| self.placements[i][k]).collect();
+            res = f(self);
+            if res.is_break() {
+                break;
+            }
+        }
+        self.placed.clear();
+        self.known = Some(known);
+        return res;
+    }
+
+    // A board whose solutions are those of this one that include `placed`.
+    pub(crate) fn with_placed(&self, placed: &[Placement]) -> Board {
+        let mut res = self.clone();
+        for p in placed {
+            res.placements[p.piece] = vec![*p];
+        }
+        for cell in &mut res.by_cell {
+            cell.clear();
+        }
+        for p in res.placements.iter().flatten() {
+            res.by_cell[p.mask.trailing_zeros() as usize].push(*p);
+        }
+        return res;
+    }
+
+    fn solution(&self, class: usize) -> Solution {
+        return Solution { placements: self.placed.clone(), grid: self.grid(), class };
+    }
+
+    // The rotations and reflections that map the board, targets included,
+    // onto itself, and every solution onto another one: they mustn't turn
+    // a piece in a way its motion rules out.
+    pub fn symmetries(&self) -> Vec<Transform> {
+        return symmetry::symmetries(&self.layout).into_iter()
+            .filter(|t| self.pieces.iter().all(|orientations| {
+                return orientations.iter()
+                    .all(|o| orientations.iter().any(|q| q.cells == t.grid(&o.cells)));
+            }))
+            .collect();
+    }
+
+    fn grow(&self, m: Mask) -> Mask {
+        let w = self.width();
+        return m | (m << w) | (m >> w)
+            | ((m & !self.col_last) << 1) | ((m & !self.col_first) >> 1);
+    }
+
+    // True if some connected empty region can't be filled by any subset of
+    // the pieces not in `used`, allowing for cells that may stay empty.
+    fn dead(&self, used: u64) -> bool {
+        let mut sums: u128 = 1;
+        let mut area = 0;
+        for (i, &size) in self.sizes.iter().enumerate() {
+            if used & (1 << i) == 0 {
+                sums |= sums.checked_shl(size as u32).unwrap_or(0);
+                area += size;
+            }
+        }
+        let mut free = self.cells & !self.occupied;
+        let spare = match (free.count_ones() as usize).checked_sub(area) {
+            Some(spare) => spare,
+            None => return true,
+        };
+        while free != 0 {
+            let mut region = free & free.wrapping_neg();
+            loop {
+                let next = self.grow(region) & free;
+                if next == region {
+                    break;
+                }
+                region = next;
+            }
+            free &= !region;
+            let size = region.count_ones() as usize;
+            let lo = size.saturating_sub(spare);
+            if (sums >> lo) & ((1 << (size - lo + 1)) - 1) == 0 {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    // Places `p` and runs `f` unless pruning finds the result hopeless.
+    // `used` is the set of placed pieces including `p`.
+    fn place<F>(&mut self, p: &Placement, used: u64, f: F) -> ControlFlow<()>
+        where F: FnOnce(&mut Board) -> ControlFlow<()>
+    {
+        let mut res = ControlFlow::Continue(());
+        self.occupied |= p.mask;
+        if self.prune && self.dead(used) {
+            self.pruned += 1;
+        } else {
+            self.placed.push(*p);
+            res = f(self);
+            self.placed.pop();
+        }
+        self.occupied ^= p.mask;
+        return res;
+    }
+
+    fn _solve_dfs(&mut self, placements: &Vec<Vec<Placement>>, piece_id: usize,
+                  f: &mut Visitor) -> ControlFlow<()> {
+        self.calls += 1;
+        if self.budget.exhausted(self.calls) {
+            return ControlFlow::Break(());
+        }
+        if piece_id == placements.len() {
+            return f(self);
+        }
+        for p in &placements[piece_id] {
+            if p.mask & self.occupied != 0 {
+                continue;
+            }
+            self.place(p, u64::MAX >> (63 - piece_id), |b| b._solve_dfs(placements, piece_id + 1, f))?;
+        }
+        return ControlFlow::Continue(());
+    }
+
+    // How many more cells may be left uncovered, for puzzles where the
+    // pieces don't fill the whole board.
+    fn spare(&self, used: u64) -> Option<usize> {
+        let free = (self.cells & !self.occupied).count_ones() as usize;
+        let area: usize = self.sizes.iter().enumerate()
+            .filter(|&(i, _)| used & (1 << i) == 0)
+            .map(|(_, size)| size)
+            .sum();
+        return free.checked_sub(area);
+    }
+
+    fn first_empty(&self) -> usize {
+        return (self.cells & !self.occupied).trailing_zeros() as usize;
+    }
+
+    fn _solve_cell(&mut self, by_cell: &Vec<Vec<Placement>>, used: u64,
+                   f: &mut Visitor) -> ControlFlow<()> {
+        self.calls += 1;
+        if self.budget.exhausted(self.calls) {
+            return ControlFlow::Break(());
+        }
+        if used.count_ones() as usize == self.pieces.len() {
+            return f(self);
+        }
+        let cell = self.first_empty();
+        for p in &by_cell[cell] {
+            if used & (1 << p.piece) != 0 || p.mask & self.occupied != 0 {
+                continue;
+            }
+            let used = used | (1 << p.piece);
+            self.place(p, used, |b| b._solve_cell(by_cell, used, f))?;
+        }
+        if self.spare(used).is_some_and(|spare| spare > 0) {
+            self.occupied |= 1 << cell;
+            let res = self._solve_cell(by_cell, used, f);
+            self.occupied ^= 1 << cell;
+            return res;
+        }
+        return ControlFlow::Continue(());
+    }
+
+    // Exact cover: one column per piece, one per open cell. When the pieces
+    // can't fill every open cell the cell columns become secondary, so the
+    // solution set stays the same as the DFS one.
+    fn build_dlx(&self) -> (dlx::Dlx, Vec<Placement>) {
+        let npieces = self.placements.len();
+        let mut cols = [usize::MAX; Mask::BITS as usize];
+        let mut ncells = 0;
+        for (r, c) in self.coords() {
+            if self.bit(r, c) & self.occupied == 0 {
+                cols[r * self.width() + c] = npieces + ncells;
+                ncells += 1;
+            }
+        }
+        let area: usize = self.sizes.iter().sum();
+        let primary = if area == ncells { npieces + ncells } else { npieces };
+        let mut dlx = dlx::Dlx::new(primary, npieces + ncells - primary);
+
+        let mut rows = vec![];
+        for p in self.placements.iter().flatten() {
+            if p.mask & self.occupied != 0 {
+                continue;
+            }
+            let mut row = vec![p.piece];
+            let mut m = p.mask;
+            while m != 0 {
+                row.push(cols[m.trailing_zeros() as usize]);
+                m &= m - 1;
+            }
+            dlx.add_row(&row);
+            rows.push(*p);
+        }
+        return (dlx, rows);
+    }
+
+    fn solve_dlx(&mut self, branch: Option<usize>, f: &mut Visitor) -> ControlFlow<()> {
+        let (mut dlx, rows) = self.build_dlx();
+        dlx.budget = self.budget.clone();
+        // Kept up to date at each solution, as the other solvers do.
+        let base = self.calls;
+        let found = |sol: &[usize], calls: usize| {
+            self.placed = sol.iter().map(|&row| rows[row]).collect();
+            self.calls = base + calls;
+            return f(self);
+        };
+        let res = match branch {
+            None => dlx.solve(found),
+            Some(k) => dlx.solve_branch(k, found),
+        };
+        self.placed.clear();
+        self.calls = base + dlx.calls;
+        self.budget.timed_out |= dlx.budget.timed_out;
+        return res;
+    }
+
+    // The choices at the top of the search tree, which are what `--threads`
+    // hands out to workers.
+    fn branches(&self, solver: Solver, strategy: Strategy) -> Vec<Branch> {
+        let first = match (solver, strategy) {
+            (Solver::Dlx, _) => return (0..self.build_dlx().0.branches()).map(Branch::Row).collect(),
+            (Solver::Dfs, Strategy::Piece) => &self.placements[0],
+            (Solver::Dfs, Strategy::Cell) => {
+                if self.spare(0).is_none() {
+                    return vec![];
+                }
+                &self.by_cell[self.first_empty()]
+            }
+        };
+        let mut res: Vec<_> = first.iter()
+            .filter(|p| p.mask & self.occupied == 0)
+            .map(|&p| Branch::Place(p))
+            .collect();
+        if let (Strategy::Cell, Some(1..)) = (strategy, self.spare(0)) {
+            res.push(Branch::Skip);
+        }
+        return res;
+    }
+
+    fn solve_branch(&mut self, strategy: Strategy, branch: Branch,
+                    f: &mut Visitor) -> ControlFlow<()> {
+        match (strategy, branch) {
+            (_, Branch::Row(k)) => return self.solve_dlx(Some(k), f),
+            (Strategy::Piece, Branch::Place(p)) => {
+                let placements = self.placements.clone();
+                return self.place(&p, 1, |b| b._solve_dfs(&placements, 1, f));
+            }
+            (Strategy::Cell, Branch::Place(p)) => {
+                let by_cell = self.by_cell.clone();
+                let used = 1 << p.piece;
+                return self.place(&p, used, |b| b._solve_cell(&by_cell, used, f));
+            }
+            (Strategy::Cell, Branch::Skip) => {
+                let cell = self.first_empty();
+                self.occupied |= 1 << cell;
+                let res = self._solve_cell(&self.by_cell.clone(), 0, f);
+                self.occupied ^= 1 << cell;
+                return res;
+            }
+            (Strategy::Piece, Branch::Skip) => unreachable!(),
+        }
+    }
+
+    // The class size of the solution on the board, or nothing if another
+    // solution stands for its class.
+    fn class(&self, symmetries: &[Transform]) -> Option<usize> {
+        if symmetries.len() <= 1 {
+            return Some(1);
+        }
+        let (canonical, class) = symmetry::canonical(&self.grid(), symmetries);
+        return if canonical { Some(class) } else { None };
+    }
+
+    // Each worker owns a clone of the board and takes top-level branches
+    // one at a time. Branch results are passed on in branch order as soon
+    // as they are complete, so the output matches a single thread. A branch
+    // that ran out of time ends the search once its turn comes.
+    //
+    // Workers leave out the solutions `symmetries` rule out and stop a
+    // branch once it has `limit` solutions, since no branch after it is
+    // needed then. Only the calls up to the last solution used count, so
+    // the statistics don't depend on the number of threads.
+    fn solve_parallel(&mut self, options: &Options, symmetries: &[Transform],
+                      f: &mut dyn FnMut(&Board, usize) -> ControlFlow<()>) -> ControlFlow<()> {
+        let strategy = options.strategy;
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let branches = self.branches(options.solver, strategy);
+        let next = AtomicUsize::new(0);
+        // The first branch with `limit` solutions of its own.
+        let enough = AtomicUsize::new(usize::MAX);
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let boards: Vec<Board> = (0..options.threads).map(|_| self.clone()).collect();
+        return thread::scope(|s| {
+            for mut board in boards {
+                let (tx, next, enough, branches) = (tx.clone(), &next, &enough, &branches);
+                board.budget.cancel = Some(stop.clone());
+                s.spawn(move || {
+                    while !board.budget.timed_out {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= branches.len() || i > enough.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        // Each solution with its class size and the calls
+                        // and pruned branches so far.
+                        let mut solutions = vec![];
+                        board.calls = 0;
+                        board.pruned = 0;
+                        let _ = board.solve_branch(strategy, branches[i], &mut |b| {
+                            if let Some(class) = b.class(symmetries) {
+                                solutions.push((b.placed.clone(), class, b.calls, b.pruned));
+                            }
+                            if solutions.len() >= limit {
+                                enough.fetch_min(i, Ordering::Relaxed);
+                                return ControlFlow::Break(());
+                            }
+                            return ControlFlow::Continue(());
+                        });
+                        let res = (solutions, board.calls, board.pruned, board.budget.timed_out);
+                        if tx.send((i, res)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut pending = BTreeMap::new();
+            let mut done = 0;
+            let mut res = ControlFlow::Continue(());
+            'recv: for (i, branch) in rx {
+                pending.insert(i, branch);
+                while let Some((solutions, calls, pruned, timed_out)) = pending.remove(&done) {
+                    done += 1;
+                    for (placed, class, calls, pruned) in solutions {
+                        self.placed = placed;
+                        if f(self, class).is_break() {
+                            self.calls += calls;
+                            self.pruned += pruned;
+                            res = ControlFlow::Break(());
+                            break 'recv;
+                        }
+                    }
+                    self.calls += calls;
+                    self.pruned += pruned;
+                    if timed_out {
+                        self.budget.timed_out = true;
+                        res = ControlFlow::Break(());
+                        break 'recv;
+                    }
+                }
+            }
+            stop.store(true, Ordering::Relaxed);
+            self.placed.clear();
+            return res;
+        });
+    }
+
+    // `f` gets each solution `symmetries` leave in with its class size.
+    fn run(&mut self, options: &Options, symmetries: &[Transform],
+           f: &mut dyn FnMut(&Board, usize) -> ControlFlow<()>) -> ControlFlow<()> {
+        if options.threads > 1 && self.known.is_none() {
+            // The root call, which the branches hang off.
+            self.calls += 1;
+            return self.solve_parallel(options, symmetries, f);
+        }
+        let f = &mut |b: &Board| match b.class(symmetries) {
+            Some(class) => f(b, class),
+            None => ControlFlow::Continue(()),
+        };
+        if self.known.is_some() {
+            return self.replay(f);
+        }
+        match (options.solver, options.strategy) {
+            (Solver::Dlx, _) => return self.solve_dlx(None, f),
+            (Solver::Dfs, Strategy::Piece) => return self._solve_dfs(&self.placements.clone(), 0, f),
+            (Solver::Dfs, Strategy::Cell) => {
+                if self.spare(0).is_none() {
+                    return ControlFlow::Continue(());
+                }
+                return self._solve_cell(&self.by_cell.clone(), 0, f);
+            }
+        }
+    }
+
+    // `f` also gets the class size of each solution.
+    fn search(&mut self, options: &Options,
+              f: &mut dyn FnMut(&Board, usize) -> ControlFlow<()>) -> Outcome {
+        self.calls = 0;
+        self.pruned = 0;
+        self.prune = options.prune;
+        self.budget = Budget {
+            deadline: options.timeout.map(|t| Instant::now() + t),
+            ..Budget::default()
+        };
+        if options.limit == Some(0) {
+            return Outcome::LimitReached;
+        }
+        let symmetries = if options.distinct { self.symmetries() } else { vec![Transform::IDENTITY] };
+        let mut found = 0;
+        let mut limited = false;
+        let res = self.run(options, &symmetries, &mut |b, class| {
+            found += 1;
+            f(b, class)?;
+            if options.limit.is_some_and(|limit| found >= limit) {
+                limited = true;
+                return ControlFlow::Break(());
+            }
+            return ControlFlow::Continue(());
+        });
+        if res.is_continue() {
+            return Outcome::Complete;
+        }
+        if limited {
+            return Outcome::LimitReached;
+        }
+        if self.budget.timed_out {
+            return Outcome::TimedOut;
+        }
+        return Outcome::Stopped;
+    }
+
+    // Like `solutions`, without building anything per solution.
+    pub fn count(&mut self, options: &Options) -> (usize, Outcome) {
+        let mut n = 0;
+        let res = self.search(options, &mut |_, _| {
+            n += 1;
+            return ControlFlow::Continue(());
+        });
+        return (n, res);
+    }
+
+    // Calls `f` with each solution in turn until it returns `Break`.
+    // `calls` and `pruned` are updated as the search goes.
+    pub fn solutions<F>(&mut self, options: &Options, mut f: F) -> Outcome
+        where F: FnMut(&Solution) -> ControlFlow<()>
+    {
+        return self.search(options, &mut |b, class| f(&b.solution(class)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::Motion;
+
+    // The grids of every solution, in the order they were found, and the
+    // search statistics. Cells are compared by their debug form, which is
+    // enough to sort them.
+    fn search(targets: &[&str], options: &Options) -> (Vec<Vec<String>>, usize, usize) {
+        let mut board = Board::new(&Puzzle::month_day(), targets).unwrap();
+        let mut res = vec![];
+        let _ = board.solutions(options, |s| {
+            res.push(s.grid.iter().map(|row| format!("{:?}", row)).collect());
+            return ControlFlow::Continue(());
+        });
+        return (res, board.calls, board.pruned);
+    }
+
+    fn dfs(strategy: Strategy, prune: bool) -> Options {
+        return Options { solver: Solver::Dfs, strategy, prune, ..Options::default() };
+    }
+
+    #[test]
+    fn solvers_agree() {
+        let all = [
+            dfs(Strategy::Piece, false),
+            dfs(Strategy::Piece, true),
+            dfs(Strategy::Cell, false),
+            dfs(Strategy::Cell, true),
+            Options { threads: 3, ..Options::default() },
+            Options { threads: 3, ..dfs(Strategy::Cell, true) },
+        ];
+        for (targets, n) in [(["JAN", "1"], 64), (["JUN", "15"], 57), (["DEC", "31"], 77)] {
+            let mut expected = search(&targets, &Options::default()).0;
+            expected.sort();
+            assert_eq!(expected.len(), n);
+            for options in &all {
+                let mut grids = search(&targets, options).0;
+                grids.sort();
+                assert_eq!(grids, expected, "{:?} {:?}", targets, options);
+            }
+        }
+    }
+
+    #[test]
+    fn threads_give_the_same_output() {
+        for options in [Options::default(), dfs(Strategy::Piece, true), dfs(Strategy::Cell, false)] {
+            let one = search(&["MAR", "9"], &options);
+            assert_eq!(search(&["MAR", "9"], &Options { threads: 4, ..options }), one, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn visitor_stops_the_search() {
+        let all = [Options::default(), dfs(Strategy::Piece, false), dfs(Strategy::Cell, false),
+                   Options { threads: 3, ..Options::default() }];
+        for options in &all {
+            let mut board = Board::new(&Puzzle::month_day(), &["MAR", "9"]).unwrap();
+            let mut n = 0;
+            let res = board.solutions(options, |_| {
+                n += 1;
+                return if n == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) };
+            });
+            assert_eq!((res, n), (Outcome::Stopped, 2), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn count_matches_solutions() {
+        for options in [Options::default(), dfs(Strategy::Cell, true), Options { threads: 2, ..Options::default() }] {
+            let (grids, calls, pruned) = search(&["JAN", "1"], &options);
+            let mut board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+            assert_eq!((board.count(&options), board.calls, board.pruned),
+                       ((grids.len(), Outcome::Complete), calls, pruned));
+        }
+    }
+
+    #[test]
+    fn limits() {
+        for options in [Options::default(), dfs(Strategy::Cell, true), Options { threads: 3, ..Options::default() }] {
+            for (limit, n, outcome) in [(0, 0, Outcome::LimitReached), (5, 5, Outcome::LimitReached),
+                                        (64, 64, Outcome::LimitReached), (65, 64, Outcome::Complete)] {
+                let mut board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+                let options = Options { limit: Some(limit), ..options };
+                assert_eq!(board.count(&options), (n, outcome), "{:?}", options);
+            }
+            let mut board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+            let options = Options { timeout: Some(Duration::ZERO), ..options };
+            assert_eq!(board.count(&options).1, Outcome::TimedOut, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn threads_stop_at_the_limit() {
+        let pentominoes = Puzzle::parse("pentominoes", include_str!("../puzzles/pentominoes.txt")).unwrap();
+        let runs = [
+            (Puzzle::month_day(), vec!["JAN", "1"], Options { limit: Some(5), ..Options::default() }),
+            (Puzzle::month_day(), vec!["JAN", "1"], Options { limit: Some(5), ..dfs(Strategy::Cell, true) }),
+            (pentominoes.clone(), vec![], Options { limit: Some(1), ..Options::default() }),
+            (pentominoes, vec![], Options { limit: Some(3), distinct: true, ..Options::default() }),
+        ];
+        for (puzzle, targets, options) in runs {
+            let mut counts = vec![];
+            let mut classes = vec![];
+            for threads in [1, 2, 4] {
+                let mut board = Board::new(&puzzle, &targets).unwrap();
+                let mut found = vec![];
+                let outcome = board.solutions(&Options { threads, ..options }, |s| {
+                    found.push(s.class);
+                    return ControlFlow::Continue(());
+                });
+                assert_eq!(found.len(), options.limit.unwrap(), "{:?}", options);
+                counts.push((outcome, board.calls, board.pruned));
+                classes.push(found);
+            }
+            assert!(counts.iter().all(|c| *c == counts[0]), "{:?} {:?}", options, counts);
+            assert!(classes.iter().all(|c| *c == classes[0]), "{:?} {:?}", options, classes);
+        }
+    }
+
+    #[test]
+    fn placements() {
+        let board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        for p in board.placements.iter().flatten() {
+            assert_eq!(p.mask.count_ones() as usize, board.pieces[p.piece][0].size());
+        }
+        for (cell, ps) in board.by_cell.iter().enumerate() {
+            assert!(ps.iter().all(|p| p.mask.trailing_zeros() as usize == cell));
+        }
+        assert_eq!(board.by_cell.iter().map(Vec::len).sum::<usize>(), board.placements.iter().map(Vec::len).sum());
+        assert_ne!(board.occupied & board.bit(0, 0), 0);
+        assert_eq!(board.occupied & board.bit(0, 1), 0);
+    }
+
+    #[test]
+    fn dead_regions() {
+        let mut b = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        assert!(!b.dead(0));
+        // Five cells in the bottom left corner, as if piece 0 covered them.
+        let cells = |cells: &[(usize, usize)]| cells.iter().fold(0, |m, &(r, c)| m | b.bit(r, c));
+        let open = cells(&[(5, 0), (5, 1), (6, 0), (6, 1), (6, 2)]);
+        let walled = cells(&[(5, 0), (5, 1), (5, 2), (6, 1), (6, 2)]);
+        b.occupied |= open;
+        assert!(!b.dead(1));
+        // (6, 0) is left on its own.
+        b.occupied ^= open ^ walled;
+        assert!(b.dead(1));
+    }
+
+    #[test]
+    fn weekdays() {
+        let mut board = Board::new(&Puzzle::weekday(), &["OCT", "19", "MON"]).unwrap();
+        let (n, outcome) = board.count(&Options::default());
+        assert_eq!(outcome, Outcome::Complete);
+        assert!(n > 0);
+    }
+
+    #[test]
+    fn set_targets() {
+        let puzzle = Puzzle::month_day();
+        let mut board = Board::new(&puzzle, &["JAN", "1"]).unwrap();
+        board.set_targets(&puzzle, &["DEC", "31"]).unwrap();
+        assert_eq!(board.targets, Board::new(&puzzle, &["DEC", "31"]).unwrap().targets);
+        assert_eq!(board.count(&Options::default()), (77, Outcome::Complete));
+    }
+
+    #[test]
+    fn indices() {
+        let puzzle = Puzzle::month_day();
+        let mut board = Board::new(&puzzle, &["JAN", "1"]).unwrap();
+        let shown = board.clone();
+        let mut known = vec![];
+        board.solutions(&Options::default(), |s| {
+            known.push(shown.indices(s).unwrap());
+            return ControlFlow::Continue(());
+        });
+        board.set_known(known).unwrap();
+        assert_eq!(board.count(&Options::default()), (64, Outcome::Complete));
+        // Placements another puzzle has.
+        let other = Board::new(&Puzzle::parse("test", "[board]\n..\n[pieces]\naa\n").unwrap(), &[]).unwrap();
+        let mut solution = None;
+        other.clone().solutions(&Options::default(), |s| {
+            solution = Some(s.clone());
+            return ControlFlow::Break(());
+        });
+        assert_eq!(board.indices(&solution.unwrap()), None);
+    }
+
+    #[test]
+    fn targets() {
+        let puzzle = Puzzle::month_day();
+        let board = Board::new(&puzzle, &["jan", "1"]).unwrap();
+        assert_eq!(board.targets, vec![("JAN".to_string(), 0, 0), ("1".to_string(), 2, 0)]);
+        assert_eq!(Board::new(&puzzle, &["JAN", "MON"]).err(), Some(Error::UnknownLabel("MON".to_string())));
+        assert_eq!(Board::new(&puzzle, &["JAN", "jan"]).err(), Some(Error::LabelTwice("JAN".to_string())));
+        // Any cells, not only a date.
+        assert!(Board::new(&puzzle, &["APR", "31"]).is_ok());
+        assert!(Board::new(&puzzle, &["1", "2", "3"]).is_ok());
+    }
+
+    #[test]
+    fn distinct() {
+        let puzzle = Puzzle::parse("test", "[board]\n...\n...\n...\n[pieces]\naaa\n\nbbb\n\nccc\n").unwrap();
+        let mut board = Board::new(&puzzle, &[]).unwrap();
+        assert_eq!(board.symmetries().len(), 8);
+        assert_eq!(board.count(&Options::default()), (12, Outcome::Complete));
+        for options in [Options::default(), dfs(Strategy::Cell, true), Options { threads: 2, ..Options::default() }] {
+            let mut classes = vec![];
+            let options = Options { distinct: true, ..options };
+            let outcome = board.solutions(&options, |s| {
+                classes.push(s.class);
+                return ControlFlow::Continue(());
+            });
+            assert_eq!((outcome, classes), (Outcome::Complete, vec![4, 4, 4]), "{:?}", options);
+        }
+        // Fixed pieces stay in rows, so only the transforms that keep rows
+        // as rows count.
+        let mut fixed = puzzle.clone();
+        fixed.restrict(Motion::Fixed);
+        let mut board = Board::new(&fixed, &[]).unwrap();
+        assert_eq!(board.symmetries().len(), 4);
+        let mut classes = vec![];
+        board.solutions(&Options { distinct: true, ..Options::default() }, |s| {
+            classes.push(s.class);
+            return ControlFlow::Continue(());
+        });
+        assert_eq!(classes, vec![2, 2, 2]);
+        // The targets break the symmetry.
+        let board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        assert_eq!(board.symmetries(), vec![Transform::IDENTITY]);
+    }
+
+    #[test]
+    fn sixty_four_pieces() {
+        let text = format!("[board]\n{}\n[pieces]\n{}\n", ["........"; 8].join("\n"), ["x"; 64].join("\n\n"));
+        let mut puzzle = Puzzle::parse("test", &text).unwrap();
+        for options in [Options::default(), dfs(Strategy::Piece, true), dfs(Strategy::Cell, true)] {
+            let mut board = Board::new(&puzzle, &[]).unwrap();
+            let options = Options { limit: Some(2), ..options };
+            assert_eq!(board.count(&options), (2, Outcome::LimitReached), "{:?}", options);
+        }
+        puzzle.pieces.push(puzzle.pieces[0].clone());
+        assert_eq!(Board::new(&puzzle, &[]).err(), Some(Error::TooManyPieces(65)));
+    }
+}
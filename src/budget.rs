@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+// Only looked at every this many calls, reading the clock isn't free.
+const INTERVAL: usize = 256;
+
+// Lets a running search be cut short, either by a deadline or by another
+// thread raising `cancel`.
+#[derive(Clone, Default)]
+pub struct Budget {
+    pub deadline: Option<Instant>,
+    pub cancel: Option<Arc<AtomicBool>>,
+    pub timed_out: bool,
+}
+
+impl Budget {
+    pub fn exhausted(&mut self, calls: usize) -> bool {
+        if !calls.is_multiple_of(INTERVAL) {
+            return false;
+        }
+        if self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return true;
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.timed_out = true;
+            return true;
+        }
+        return false;
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::NaiveDate;
+
+use crate::Error;
+
+pub const WEEKDAYS: [&str; 7] = [
+    "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+// February always has 29 days: the puzzle covers every date of a leap year.
+pub fn days_in_month(month: usize) -> usize {
+    return match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+}
+
+// 1 for January.
+pub fn month_name(month: usize) -> Result<&'static str, Error> {
+    return month.checked_sub(1).and_then(|m| MONTHS.get(m)).copied().ok_or(Error::InvalidMonth(month));
+}
+
+// Labels of the target cells in puzzle files.
+pub fn month_label(month: usize) -> Result<String, Error> {
+    return Ok(month_name(month)?[..3].to_uppercase());
+}
+
+pub fn day_label(day: usize) -> String {
+    return day.to_string();
+}
+
+// 0 for Sunday.
+pub fn weekday_label(weekday: usize) -> Result<String, Error> {
+    let name = WEEKDAYS.get(weekday).ok_or(Error::BadWeekday(weekday.to_string()))?;
+    return Ok(name[..3].to_uppercase());
+}
+
+// Range checks only: the board has a cell for 31 April.
+pub fn check_range(day: usize, month: usize) -> Result<(), Error> {
+    if !(1..=12).contains(&month) {
+        return Err(Error::InvalidMonth(month));
+    }
+    if !(1..=31).contains(&day) {
+        return Err(Error::InvalidDay(day));
+    }
+    return Ok(());
+}
+
+pub fn check_date(day: usize, month: usize) -> Result<(), Error> {
+    check_range(day, month)?;
+    if day > days_in_month(month) {
+        return Err(Error::ImpossibleDate { day, month });
+    }
+    return Ok(());
+}
+
+// `YYYY-MM-DD` or `MM-DD`, as (year, month, day). A date with a year has
+// to exist; one without only has to be on the board, like 31 April, and
+// the caller decides whether that will do.
+pub fn parse(s: &str) -> Result<(Option<i32>, usize, usize), Error> {
+    let err = || Error::BadDate(s.to_string());
+    let nums = s.split('-')
+        .map(|p| p.parse::<u32>().map_err(|_| err()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (y, m, d) = match nums[..] {
+        [y, m, d] => (Some(i32::try_from(y).map_err(|_| err())?), m, d),
+        [m, d] => (None, m, d),
+        _ => return Err(err()),
+    };
+    match y {
+        Some(y) => { NaiveDate::from_ymd_opt(y, m, d).ok_or_else(err)?; }
+        None => check_range(d as usize, m as usize).map_err(|_| err())?,
+    }
+    return Ok((y, m as usize, d as usize));
+}
+
+// 0 for Sunday. Takes a number or any prefix of at least two letters of
+// the English name.
+pub fn parse_weekday(s: &str) -> Result<usize, Error> {
+    if let Ok(n) = s.parse::<usize>() {
+        if n < 7 {
+            return Ok(n);
+        }
+    }
+    let name = s.to_lowercase();
+    return WEEKDAYS.iter()
+        .position(|w| name.len() >= 2 && w.to_lowercase().starts_with(&name))
+        .ok_or(Error::BadWeekday(s.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_dates() {
+        assert_eq!(check_range(31, 4), Ok(()));
+        assert_eq!(check_date(31, 4), Err(Error::ImpossibleDate { day: 31, month: 4 }));
+        assert_eq!(check_date(29, 2), Ok(()));
+        assert_eq!(check_date(30, 2), Err(Error::ImpossibleDate { day: 30, month: 2 }));
+        assert_eq!(check_range(1, 0), Err(Error::InvalidMonth(0)));
+        assert_eq!(check_range(1, 13), Err(Error::InvalidMonth(13)));
+        assert_eq!(check_range(0, 1), Err(Error::InvalidDay(0)));
+        assert_eq!(check_range(32, 1), Err(Error::InvalidDay(32)));
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(month_name(1), Ok("January"));
+        assert_eq!(month_name(12), Ok("December"));
+        assert_eq!(month_name(0), Err(Error::InvalidMonth(0)));
+        assert_eq!(month_name(13), Err(Error::InvalidMonth(13)));
+        assert_eq!(month_label(2), Ok("FEB".to_string()));
+        assert_eq!(weekday_label(0), Ok("SUN".to_string()));
+        assert_eq!(weekday_label(7), Err(Error::BadWeekday("7".to_string())));
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(parse("2026-10-19"), Ok((Some(2026), 10, 19)));
+        assert_eq!(parse("2024-02-29"), Ok((Some(2024), 2, 29)));
+        assert_eq!(parse("10-19"), Ok((None, 10, 19)));
+        // Without a year, any day the board has is: 29 February, and 31
+        // April for --allow-impossible.
+        assert_eq!(parse("02-29"), Ok((None, 2, 29)));
+        assert_eq!(parse("04-31"), Ok((None, 4, 31)));
+        for s in ["2026-02-29", "2026-04-31", "13-01", "01-32", "10", "a-b", "1-2-3-4", ""] {
+            assert_eq!(parse(s), Err(Error::BadDate(s.to_string())), "{}", s);
+        }
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(parse_weekday("0"), Ok(0));
+        assert_eq!(parse_weekday("Mo"), Ok(1));
+        assert_eq!(parse_weekday("saturday"), Ok(6));
+        assert_eq!(parse_weekday("t"), Err(Error::BadWeekday("t".to_string())));
+        assert_eq!(parse_weekday("7"), Err(Error::BadWeekday("7".to_string())));
+    }
+}
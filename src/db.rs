@@ -0,0 +1,340 @@
+// Every solution for every date, worked out once and kept in a file.
+//
+// The file is little endian. It starts with "APADB", a format version, the
+// checksum of the puzzle it was built from and the number of pieces and of
+// entries. An index follows with each entry's target labels (a count, then
+// each as a length and UTF-8 bytes), solution count and offset in the file.
+// The solutions come last, a u16 per piece indexing the placements
+// `Board::new` works out for the puzzle, so a lookup only reads its entry.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::board::{Board, Options, Outcome};
+use crate::piece::{Cell, Motion};
+use crate::puzzle::Puzzle;
+use crate::Error;
+
+const MAGIC: &[u8] = b"APADB";
+const VERSION: u8 = 1;
+
+pub struct Entry {
+    // Sorted, as `Puzzle::label` spells them.
+    pub targets: Vec<String>,
+    pub solutions: Vec<Vec<usize>>,
+}
+
+// A database being built.
+pub struct Db {
+    pub checksum: u64,
+    pub pieces: usize,
+    pub entries: Vec<Entry>,
+}
+
+// A database file opened for lookups, with only the index read.
+pub struct Index {
+    pub checksum: u64,
+    pub pieces: usize,
+    // Target labels, solution count and offset of each entry.
+    entries: Vec<(Vec<String>, usize, u64)>,
+    file: BufReader<File>,
+}
+
+// FNV-1a of the board, labels and pieces, which is all the placement
+// indices depend on.
+pub fn checksum(puzzle: &Puzzle) -> u64 {
+    let mut text = String::new();
+    for row in &puzzle.board {
+        text.extend(row.iter().map(|c| if *c == Cell::Blocked { '#' } else { '.' }));
+        text.push('\n');
+    }
+    for (label, r, c) in &puzzle.labels {
+        text.push_str(&format!("label {} {} {}\n", label, r, c));
+    }
+    for p in &puzzle.pieces {
+        let motion = match p.motion {
+            Motion::Free => "free",
+            Motion::NoFlip => "no-flip",
+            Motion::Fixed => "fixed",
+        };
+        text.push_str(&format!("piece {} {} {}\n", p.name, p.glyph, motion));
+        for row in &p.cells {
+            text.extend(row.iter().map(|&x| if x { 'x' } else { '.' }));
+            text.push('\n');
+        }
+    }
+    let mut res: u64 = 0xcbf29ce484222325;
+    for b in text.bytes() {
+        res = (res ^ b as u64).wrapping_mul(0x100000001b3);
+    }
+    return res;
+}
+
+fn key(board: &Board) -> Vec<String> {
+    let mut res: Vec<String> = board.targets.iter().map(|t| t.0.clone()).collect();
+    res.sort();
+    return res;
+}
+
+// Reads the file a piece at a time.
+struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    fn take(&mut self, n: usize) -> Result<Vec<u8>, Error> {
+        let mut res = vec![0; n];
+        self.inner.read_exact(&mut res).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::BadDb("file is cut short".to_string()),
+            _ => Error::Io(e.to_string()),
+        })?;
+        return Ok(res);
+    }
+
+    fn u8(&mut self) -> Result<usize, Error> {
+        return Ok(self.take(1)?[0] as usize);
+    }
+
+    fn u32(&mut self) -> Result<usize, Error> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize);
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+}
+
+impl Db {
+    pub fn new(puzzle: &Puzzle) -> Db {
+        return Db { checksum: checksum(puzzle), pieces: puzzle.pieces.len(), entries: vec![] };
+    }
+
+    // Finds every solution for the board's targets and adds them. The
+    // search has to finish, or the entry would be missing solutions.
+    pub fn add(&mut self, board: &mut Board, options: &Options) -> Result<usize, Error> {
+        let options = Options { limit: None, distinct: false, ..*options };
+        let shown = board.clone();
+        let mut solutions = vec![];
+        let outcome = board.solutions(&options, |s| {
+            solutions.push(shown.indices(s));
+            return std::ops::ControlFlow::Continue(());
+        });
+        if outcome != Outcome::Complete {
+            return Err(Error::Unfinished(key(board).join(" ")));
+        }
+        let Some(solutions) = solutions.into_iter().collect::<Option<Vec<_>>>() else {
+            return Err(Error::BadDb("a solution doesn't match the board's placements".to_string()));
+        };
+        let n = solutions.len();
+        self.entries.push(Entry { targets: key(board), solutions });
+        return Ok(n);
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let too_big = |what: &str| Error::BadDb(format!("too many {} to store", what));
+        let mut index = vec![];
+        for entry in &self.entries {
+            index.push(u8::try_from(entry.targets.len()).map_err(|_| too_big("targets"))?);
+            for label in &entry.targets {
+                index.push(u8::try_from(label.len()).map_err(|_| too_big("characters in a label"))?);
+                index.extend(label.as_bytes());
+            }
+            index.extend(u32::try_from(entry.solutions.len()).map_err(|_| too_big("solutions"))?.to_le_bytes());
+            // The offset, filled in below.
+            index.extend([0; 8]);
+        }
+        let mut head = MAGIC.to_vec();
+        head.push(VERSION);
+        head.extend(self.checksum.to_le_bytes());
+        head.push(u8::try_from(self.pieces).map_err(|_| too_big("pieces"))?);
+        head.extend(u32::try_from(self.entries.len()).map_err(|_| too_big("entries"))?.to_le_bytes());
+
+        let mut offset = (head.len() + index.len()) as u64;
+        let mut at = 0;
+        for entry in &self.entries {
+            at += 1 + entry.targets.iter().map(|l| 1 + l.len()).sum::<usize>() + 4;
+            index[at..at + 8].copy_from_slice(&offset.to_le_bytes());
+            at += 8;
+            offset += (entry.solutions.len() * self.pieces * 2) as u64;
+        }
+        let placements = || self.entries.iter().flat_map(|e| e.solutions.iter().flatten());
+        if placements().any(|&k| u16::try_from(k).is_err()) {
+            return Err(too_big("placements"));
+        }
+
+        let io = |e: io::Error| Error::Io(format!("{}: {}", path.display(), e));
+        let mut out = BufWriter::new(File::create(path).map_err(io)?);
+        out.write_all(&head).map_err(io)?;
+        out.write_all(&index).map_err(io)?;
+        for &k in placements() {
+            out.write_all(&(k as u16).to_le_bytes()).map_err(io)?;
+        }
+        return out.flush().map_err(io);
+    }
+}
+
+impl Index {
+    pub fn open(path: &Path) -> Result<Index, Error> {
+        let file = File::open(path).map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?;
+        let len = file.metadata().map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?.len();
+        let mut r = Reader { inner: BufReader::new(file) };
+        if r.take(MAGIC.len()).ok().as_deref() != Some(MAGIC) {
+            return Err(Error::BadDb("not a solution database".to_string()));
+        }
+        let version = r.u8()?;
+        if version != VERSION as usize {
+            return Err(Error::BadDb(format!("format version {}, expected {}", version, VERSION)));
+        }
+        let checksum = r.u64()?;
+        let pieces = r.u8()?;
+        if pieces == 0 {
+            return Err(Error::BadDb("database has no pieces".to_string()));
+        }
+        let mut entries = vec![];
+        for _ in 0..r.u32()? {
+            let mut targets = vec![];
+            for _ in 0..r.u8()? {
+                let n = r.u8()?;
+                let label = String::from_utf8(r.take(n)?)
+                    .map_err(|_| Error::BadDb("label isn't UTF-8".to_string()))?;
+                targets.push(label);
+            }
+            entries.push((targets, r.u32()?, r.u64()?));
+        }
+        // The entries' solutions follow the index and each other to the end
+        // of the file.
+        let mut end = r.inner.stream_position().map_err(|e| Error::Io(e.to_string()))?;
+        let size = Error::BadDb("index doesn't match the file size".to_string());
+        for &(_, n, offset) in &entries {
+            if offset != end {
+                return Err(Error::BadDb("entries don't follow each other".to_string()));
+            }
+            end = (n as u64).checked_mul(pieces as u64 * 2).and_then(|k| end.checked_add(k))
+                .filter(|&end| end <= len).ok_or(size.clone())?;
+        }
+        if end != len {
+            return Err(size);
+        }
+        return Ok(Index { checksum, pieces, entries, file: r.inner });
+    }
+
+    // Errors unless the database was built from `puzzle`.
+    pub fn check(&self, puzzle: &Puzzle) -> Result<(), Error> {
+        if self.checksum != checksum(puzzle) || self.pieces != puzzle.pieces.len() {
+            return Err(Error::DbMismatch);
+        }
+        return Ok(());
+    }
+
+    // Has `board` go through the solutions in the database for its targets
+    // instead of searching.
+    pub fn load(&mut self, board: &mut Board) -> Result<(), Error> {
+        let key = key(board);
+        let &(_, n, offset) = self.entries.iter().find(|e| e.0 == key)
+            .ok_or(Error::NotInDb(key.join(" ")))?;
+        self.file.seek(SeekFrom::Start(offset)).map_err(|e| Error::Io(e.to_string()))?;
+        let bytes = Reader { inner: &mut self.file }.take(n * self.pieces * 2)?;
+        let solutions = bytes.chunks(self.pieces * 2)
+            .map(|s| s.chunks(2).map(|k| u16::from_le_bytes([k[0], k[1]]) as usize).collect())
+            .collect();
+        return board.set_known(solutions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const PUZZLE: &str = "[board]\n....\n....\n[pieces]\naaa\n\nbbb\n[labels]\nX 0 3\nY 1 0\nZ 0 0\n";
+
+    fn temp(name: &str) -> PathBuf {
+        return std::env::temp_dir().join(format!("apad-{}-{}.db", std::process::id(), name));
+    }
+
+    fn grids(board: &mut Board) -> Vec<Vec<Vec<Cell>>> {
+        let mut res = vec![];
+        board.solutions(&Options::default(), |s| {
+            res.push(s.grid.clone());
+            return ControlFlow::Continue(());
+        });
+        return res;
+    }
+
+    // A database with entries for X Y and Y Z.
+    fn build(path: &Path) -> Puzzle {
+        let puzzle = Puzzle::parse("test", PUZZLE).unwrap();
+        let mut db = Db::new(&puzzle);
+        for targets in [["X", "Y"], ["Z", "Y"]] {
+            let mut board = Board::new(&puzzle, &targets).unwrap();
+            assert_eq!(db.add(&mut board, &Options::default()), Ok(2));
+        }
+        db.write(path).unwrap();
+        return puzzle;
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp("round-trip");
+        let puzzle = build(&path);
+        let mut index = Index::open(&path).unwrap();
+        index.check(&puzzle).unwrap();
+        for targets in [["Y", "X"], ["Y", "Z"]] {
+            let mut board = Board::new(&puzzle, &targets).unwrap();
+            let expected = grids(&mut board);
+            index.load(&mut board).unwrap();
+            assert_eq!(grids(&mut board), expected);
+            assert_eq!(board.calls, 0);
+        }
+        let mut board = Board::new(&puzzle, &["X", "Z"]).unwrap();
+        assert_eq!(index.load(&mut board), Err(Error::NotInDb("X Z".to_string())));
+        let mut other = puzzle.clone();
+        other.restrict(Motion::Fixed);
+        assert_eq!(index.check(&other), Err(Error::DbMismatch));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bad_files() {
+        let path = temp("bad-files");
+        build(&path);
+        let bytes = std::fs::read(&path).unwrap();
+        let open = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            return Index::open(&path).err();
+        };
+        let bad = |s: &str| Some(Error::BadDb(s.to_string()));
+        assert_eq!(open(b"PNG"), bad("not a solution database"));
+        assert_eq!(open(&[MAGIC, &[9]].concat()), bad("format version 9, expected 1"));
+        assert_eq!(open(&bytes[..20]), bad("file is cut short"));
+        assert_eq!(open(&bytes[..bytes.len() - 1]), bad("index doesn't match the file size"));
+        assert_eq!(open(&[&bytes[..], &[0]].concat()), bad("index doesn't match the file size"));
+        // The piece count follows the magic, version and checksum.
+        let mut none = bytes.clone();
+        none[14] = 0;
+        assert_eq!(open(&none), bad("database has no pieces"));
+        // The last entry's offset ends the index, after its solution count.
+        // Moving it or making the count huge is caught before anything is
+        // read.
+        let mut moved = bytes.clone();
+        let at = bytes.len() - 4 * 2 * 2 - 8;
+        moved[at] += 2;
+        assert_eq!(open(&moved), bad("entries don't follow each other"));
+        let mut huge = bytes.clone();
+        huge[at - 4..at].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(open(&huge), bad("index doesn't match the file size"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn too_many_placements() {
+        let path = temp("too-many-placements");
+        let db = Db { checksum: 0, pieces: 1, entries: vec![Entry { targets: vec![], solutions: vec![vec![70000]] }] };
+        assert_eq!(db.write(&path), Err(Error::BadDb("too many placements to store".to_string())));
+        // Nothing is left behind.
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,282 @@
+// Knuth's Dancing Links over flat index arrays. Node 0 is the root, nodes
+// 1..=ncols are the column headers, the rest are the 1s of the matrix.
+// Secondary columns are never linked into the root list: they may be
+// covered at most once but don't have to be.
+
+use std::ops::ControlFlow;
+
+use crate::budget::Budget;
+
+const ROOT: usize = 0;
+
+pub struct Dlx {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    col: Vec<usize>,
+    row: Vec<usize>,
+    size: Vec<usize>,
+    rows: usize,
+    pub calls: usize,
+    pub budget: Budget,
+}
+
+impl Dlx {
+    pub fn new(primary: usize, secondary: usize) -> Dlx {
+        let n = primary + secondary + 1;
+        let mut res = Dlx {
+            left: (0..n).collect(),
+            right: (0..n).collect(),
+            up: (0..n).collect(),
+            down: (0..n).collect(),
+            col: (0..n).collect(),
+            row: vec![usize::MAX; n],
+            size: vec![0; n],
+            rows: 0,
+            calls: 0,
+            budget: Budget::default(),
+        };
+        for i in 0..=primary {
+            res.left[i] = if i == 0 { primary } else { i - 1 };
+            res.right[i] = if i == primary { ROOT } else { i + 1 };
+        }
+        return res;
+    }
+
+    pub fn add_row(&mut self, cols: &[usize]) {
+        let first = self.col.len();
+        for (k, &c) in cols.iter().enumerate() {
+            let h = c + 1;
+            let x = self.col.len();
+            self.col.push(h);
+            self.row.push(self.rows);
+            self.up.push(self.up[h]);
+            self.down.push(h);
+            let u = self.up[h];
+            self.down[u] = x;
+            self.up[h] = x;
+            self.left.push(if k == 0 { x } else { x - 1 });
+            self.right.push(first);
+            if k > 0 {
+                self.right[x - 1] = x;
+            }
+            self.left[first] = x;
+            self.size[h] += 1;
+        }
+        self.rows += 1;
+    }
+
+    fn cover(&mut self, c: usize) {
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = r;
+        self.left[r] = l;
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.down[u] = d;
+                self.up[d] = u;
+                self.size[self.col[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, c: usize) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                let (u, d) = (self.up[j], self.down[j]);
+                self.size[self.col[j]] += 1;
+                self.down[u] = j;
+                self.up[d] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        let (l, r) = (self.left[c], self.right[c]);
+        self.right[l] = c;
+        self.left[r] = c;
+    }
+
+    // Primary column with the fewest remaining rows.
+    fn choose(&self) -> usize {
+        let mut best = self.right[ROOT];
+        let mut c = self.right[best];
+        while c != ROOT {
+            if self.size[c] < self.size[best] {
+                best = c;
+            }
+            c = self.right[c];
+        }
+        return best;
+    }
+
+    fn select(&mut self, r: usize) {
+        let mut j = self.right[r];
+        while j != r {
+            self.cover(self.col[j]);
+            j = self.right[j];
+        }
+    }
+
+    fn unselect(&mut self, r: usize) {
+        let mut j = self.left[r];
+        while j != r {
+            self.uncover(self.col[j]);
+            j = self.left[j];
+        }
+    }
+
+    fn search<F>(&mut self, stack: &mut Vec<usize>, f: &mut F) -> ControlFlow<()>
+        where F: FnMut(&[usize], usize) -> ControlFlow<()>
+    {
+        self.calls += 1;
+        if self.budget.exhausted(self.calls) {
+            return ControlFlow::Break(());
+        }
+        if self.right[ROOT] == ROOT {
+            return f(stack, self.calls);
+        }
+        let c = self.choose();
+        if self.size[c] == 0 {
+            return ControlFlow::Continue(());
+        }
+        self.cover(c);
+        let mut res = ControlFlow::Continue(());
+        let mut r = self.down[c];
+        while r != c && res.is_continue() {
+            stack.push(self.row[r]);
+            self.select(r);
+            res = self.search(stack, f);
+            self.unselect(r);
+            stack.pop();
+            r = self.down[r];
+        }
+        self.uncover(c);
+        return res;
+    }
+
+    // The number of rows in the column the search branches on first. Each
+    // of them can be explored separately with `solve_branch`.
+    pub fn branches(&self) -> usize {
+        if self.right[ROOT] == ROOT {
+            return 0;
+        }
+        return self.size[self.choose()];
+    }
+
+    // Like `solve`, restricted to the subtree under the `k`-th first-level
+    // branch. The root call isn't counted in `calls`.
+    pub fn solve_branch<F>(&mut self, k: usize, mut f: F) -> ControlFlow<()>
+        where F: FnMut(&[usize], usize) -> ControlFlow<()>
+    {
+        self.calls = 0;
+        let c = self.choose();
+        self.cover(c);
+        let mut r = self.down[c];
+        for _ in 0..k {
+            r = self.down[r];
+        }
+        self.select(r);
+        let res = self.search(&mut vec![self.row[r]], &mut f);
+        self.unselect(r);
+        self.uncover(c);
+        return res;
+    }
+
+    // Calls `f` with the row indices (in `add_row` order) of every exact
+    // cover, and `calls` as it was when it was found, until it returns
+    // `Break`.
+    pub fn solve<F>(&mut self, mut f: F) -> ControlFlow<()>
+        where F: FnMut(&[usize], usize) -> ControlFlow<()>
+    {
+        self.calls = 0;
+        return self.search(&mut vec![], &mut f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn covers(dlx: &mut Dlx) -> Vec<Vec<usize>> {
+        let mut res = vec![];
+        let _ = dlx.solve(|rows, _| {
+            let mut rows = rows.to_vec();
+            rows.sort();
+            res.push(rows);
+            return ControlFlow::Continue(());
+        });
+        res.sort();
+        return res;
+    }
+
+    #[test]
+    fn knuths_example() {
+        let mut dlx = Dlx::new(7, 0);
+        for row in [[2, 4, 5].as_slice(), &[0, 3, 6], &[1, 2, 5], &[0, 3], &[1, 6], &[3, 4, 6]] {
+            dlx.add_row(row);
+        }
+        assert_eq!(covers(&mut dlx), vec![vec![0, 3, 4]]);
+        assert!(dlx.calls > 0);
+    }
+
+    #[test]
+    fn branches_split_the_search() {
+        let mut dlx = Dlx::new(2, 1);
+        for row in [[0, 2].as_slice(), &[1, 2], &[0], &[1]] {
+            dlx.add_row(row);
+        }
+        let all = covers(&mut dlx);
+        let mut res = vec![];
+        for k in 0..dlx.branches() {
+            let _ = dlx.solve_branch(k, |rows, _| {
+                let mut rows = rows.to_vec();
+                rows.sort();
+                res.push(rows);
+                return ControlFlow::Continue(());
+            });
+        }
+        res.sort();
+        assert_eq!(res, all);
+        // The matrix is restored after each branch.
+        assert_eq!(covers(&mut dlx), all);
+    }
+
+    #[test]
+    fn secondary_columns_are_optional() {
+        let mut dlx = Dlx::new(2, 1);
+        for row in [[0, 2].as_slice(), &[1, 2], &[0], &[1]] {
+            dlx.add_row(row);
+        }
+        assert_eq!(covers(&mut dlx), vec![vec![0, 3], vec![1, 2], vec![2, 3]]);
+    }
+
+    #[test]
+    fn break_stops_the_search() {
+        let mut dlx = Dlx::new(1, 0);
+        dlx.add_row(&[0]);
+        dlx.add_row(&[0]);
+        let mut n = 0;
+        let mut calls = 0;
+        let res = dlx.solve(|_, at| {
+            n += 1;
+            calls = at;
+            return ControlFlow::Break(());
+        });
+        assert_eq!((res, n), (ControlFlow::Break(()), 1));
+        assert_eq!(calls, dlx.calls);
+    }
+
+    #[test]
+    fn no_cover() {
+        let mut dlx = Dlx::new(2, 0);
+        dlx.add_row(&[0]);
+        assert!(covers(&mut dlx).is_empty());
+    }
+}
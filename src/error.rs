@@ -0,0 +1,73 @@
+use std::fmt;
+
+use crate::date;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    EmptyPiece,
+    RaggedPiece(String),
+    BoardTooLarge(usize),
+    TooManyPieces(usize),
+    RaggedBoard,
+    Io(String),
+    Parse { source: String, line: usize, column: usize, message: String },
+    UnknownLabel(String),
+    BadLabel(String),
+    LabelTwice(String),
+    InvalidMonth(usize),
+    InvalidDay(usize),
+    ImpossibleDate { day: usize, month: usize },
+    BadDate(String),
+    BadOffset(i64),
+    NotInYear { day: usize, month: usize, year: i32 },
+    BadWeekday(String),
+    UnknownWeekday,
+    NoWeekdayCells,
+    BadPosition(String),
+    NotASolution(usize),
+    BadDb(String),
+    DbMismatch,
+    NotInDb(String),
+    Unfinished(String),
+    NeedsDfs(&'static str),
+    NeedsSvg(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyPiece => write!(f, "piece has no cells"),
+            Error::RaggedPiece(name) => write!(f, "rows of piece {} differ in length", name),
+            Error::BoardTooLarge(n) => write!(f, "board has {} cells, at most 64 are supported", n),
+            Error::TooManyPieces(n) => write!(f, "puzzle has {} pieces, at most 64 are supported", n),
+            Error::RaggedBoard => write!(f, "rows of the board differ in length"),
+            Error::Io(s) => write!(f, "{}", s),
+            Error::Parse { source, line, column, message } =>
+                write!(f, "{}:{}:{}: {}", source, line, column, message),
+            Error::UnknownLabel(s) => write!(f, "puzzle has no cell labelled {}", s),
+            Error::BadLabel(s) => write!(f, "label {} isn't on an open cell", s),
+            Error::LabelTwice(s) => write!(f, "label {} given twice", s),
+            Error::InvalidMonth(m) => write!(f, "month must be between 1 and 12, got {}", m),
+            Error::InvalidDay(d) => write!(f, "day must be between 1 and 31, got {}", d),
+            Error::ImpossibleDate { day, month } => write!(f, "{} has only {} days, got {}",
+                date::month_name(*month).unwrap_or("the month"), date::days_in_month(*month), day),
+            Error::BadDate(s) => write!(f, "invalid date {:?}, expected YYYY-MM-DD or MM-DD", s),
+            Error::BadOffset(n) => write!(f, "an offset of {:+} days is out of range", n),
+            Error::NotInYear { day, month, year } => write!(f, "{} {} {} doesn't exist",
+                day, date::month_name(*month).unwrap_or("?"), year),
+            Error::BadWeekday(s) => write!(f, "invalid weekday {:?}", s),
+            Error::UnknownWeekday => write!(f, "can't tell the weekday of this date, give it with --weekday"),
+            Error::NoWeekdayCells => write!(f, "this puzzle has no weekday cells"),
+            Error::BadPosition(s) => write!(f, "invalid position: {}", s),
+            Error::NotASolution(n) => write!(f, "not a solution, {} problem{} found", n, if *n == 1 { "" } else { "s" }),
+            Error::BadDb(s) => write!(f, "invalid solution database: {}", s),
+            Error::DbMismatch => write!(f, "the solution database was built for a different puzzle"),
+            Error::NotInDb(s) => write!(f, "the solution database has nothing for {}", s),
+            Error::Unfinished(s) => write!(f, "the search for {} didn't finish", s),
+            Error::NeedsDfs(flag) => write!(f, "{} only applies to --solver dfs", flag),
+            Error::NeedsSvg(flag) => write!(f, "{} only applies to --format svg", flag),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
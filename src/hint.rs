@@ -0,0 +1,267 @@
+// Suggestions for a partly solved board: a piece that goes with what's
+// already placed, or which placed piece is in the way.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use crate::board::{Board, Mask, Options, Outcome, Placement, Solution};
+use crate::piece::Cell;
+use crate::text;
+use crate::Error;
+
+#[derive(Clone, Debug)]
+pub enum Hint {
+    // Every piece is on the board.
+    Solved,
+    // A placement that some completion of the position uses.
+    Place(Placement),
+    // The position can't be completed. Taking away any one of these placed
+    // pieces would make it possible again; if none, more have to go.
+    DeadEnd(Vec<usize>),
+    // The search ran out of time.
+    Unknown,
+}
+
+// What `rows` reads a label as.
+pub(crate) const LABEL: char = ' ';
+
+// The cells of a board drawn one character per cell, the way the emoji
+// style prints it: a labelled cell may also be its two character label,
+// whichever targets the drawing was made for. Cells read as a label come
+// back as `LABEL`.
+pub(crate) fn rows(board: &Board, text: &str) -> Result<Vec<Vec<char>>, Error> {
+    let bad = |s: String| Err(Error::BadPosition(s));
+    let lines: Vec<(usize, &str)> = text.lines()
+        .map(str::trim_end)
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with("//"))
+        .collect();
+    if lines.len() != board.height() {
+        return bad(format!("expected {} rows, got {}", board.height(), lines.len()));
+    }
+    let mut res = vec![];
+    for (r, &(n, line)) in lines.iter().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut row = vec![];
+        let mut at = 0;
+        for c in 0..board.width() {
+            let label = board.labels.iter()
+                .filter(|l| (l.1, l.2) == (r, c))
+                .map(|l| text::short(&l.0).trim_end().chars().collect::<Vec<char>>())
+                .find(|label| label.len() > 1 && chars[at..].starts_with(label));
+            if label.is_some() {
+                row.push(LABEL);
+                // A short label's padding may have been trimmed off.
+                at = (at + 2).min(chars.len());
+            } else if let Some(&ch) = chars.get(at) {
+                row.push(ch);
+                at += 1;
+            } else {
+                break;
+            }
+        }
+        if row.len() != board.width() || at != chars.len() {
+            return bad(format!("line {} has {} characters, which don't make the {} cells of row {}",
+                               n + 1, chars.len(), board.width(), r + 1));
+        }
+        res.push(row);
+    }
+    return Ok(res);
+}
+
+// Which piece each glyph stands for. Drawings can't tell apart pieces that
+// share a glyph, or a piece drawn as '.' from a free cell.
+pub(crate) fn glyphs(board: &Board) -> Result<HashMap<char, usize>, Error> {
+    let mut res = HashMap::new();
+    for (i, p) in board.pieces.iter().enumerate() {
+        if p[0].glyph == '.' {
+            return Err(Error::BadPosition(format!("piece {} is drawn as '.', the same as a free cell", p[0].name)));
+        }
+        if res.insert(p[0].glyph, i).is_some() {
+            return Err(Error::BadPosition(format!("more than one piece is drawn as {}", p[0].glyph)));
+        }
+    }
+    return Ok(res);
+}
+
+// Reads a position drawn like a piece: one character per board cell, '.'
+// (or a label) for a free cell and a piece's glyph for the cells it covers.
+// Blocked and target cells may hold anything but a glyph.
+pub fn position(board: &Board, text: &str) -> Result<Vec<Placement>, Error> {
+    let bad = |s: String| Err(Error::BadPosition(s));
+    let rows = rows(board, text)?;
+    let glyphs = glyphs(board)?;
+
+    let mut masks: Vec<Mask> = vec![0; board.pieces.len()];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, ch) in row.iter().enumerate() {
+            let piece = glyphs.get(ch);
+            match (&board.layout()[r][c], piece) {
+                (Cell::Empty, Some(&i)) => masks[i] |= board.bit(r, c),
+                (Cell::Empty, None) if *ch == '.' || *ch == LABEL => {}
+                (Cell::Empty, None) => return bad(format!("row {} column {}: no piece is drawn as {}", r + 1, c + 1, ch)),
+                (_, Some(_)) => return bad(format!("row {} column {}: {} on a cell that can't be covered", r + 1, c + 1, ch)),
+                (_, None) => {}
+            }
+        }
+    }
+
+    let mut res = vec![];
+    for (i, &mask) in masks.iter().enumerate() {
+        if mask == 0 {
+            continue;
+        }
+        match board.placement(i, mask) {
+            Some(p) => res.push(p),
+            None => return bad(format!("the cells drawn as {} aren't the piece {}",
+                                       board.pieces[i][0].glyph, board.pieces[i][0].name)),
+        }
+    }
+    return Ok(res);
+}
+
+// Some solution that includes `placed`, if there is one and the search
+// finishes in time.
+pub fn completion(board: &Board, placed: &[Placement], options: &Options)
+    -> (Option<Solution>, Outcome)
+{
+    let mut b = board.with_placed(placed);
+    let options = Options { limit: Some(1), distinct: false, ..*options };
+    let mut res = None;
+    let outcome = b.solutions(&options, |s| {
+        res = Some(s.clone());
+        return ControlFlow::Continue(());
+    });
+    return (res, outcome);
+}
+
+pub fn hint(board: &Board, placed: &[Placement], options: &Options) -> Hint {
+    if placed.len() == board.pieces.len() {
+        return Hint::Solved;
+    }
+    match completion(board, placed, options) {
+        // The new placement nearest the top left, where the player most
+        // likely is.
+        (Some(s), _) => {
+            let new = s.placements.iter()
+                .filter(|p| !placed.iter().any(|q| q.piece == p.piece))
+                .min_by_key(|p| p.mask.trailing_zeros());
+            return Hint::Place(*new.unwrap());
+        }
+        (None, Outcome::TimedOut) => return Hint::Unknown,
+        (None, _) => {}
+    }
+    let mut culprits = vec![];
+    for (k, p) in placed.iter().enumerate() {
+        let rest: Vec<Placement> = placed.iter().enumerate()
+            .filter(|&(j, _)| j != k)
+            .map(|(_, q)| *q)
+            .collect();
+        match completion(board, &rest, options) {
+            (Some(_), _) => culprits.push(p.piece),
+            (None, Outcome::TimedOut) => return Hint::Unknown,
+            (None, _) => {}
+        }
+    }
+    return Hint::DeadEnd(culprits);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::puzzle::Puzzle;
+
+    // Six dominoes on two rows of six.
+    fn dominoes() -> Board {
+        let pieces = ["aa", "bb", "cc", "dd", "ee", "ff"].join("\n\n");
+        let text = format!("[board]\n......\n......\n[pieces]\n{}\n", pieces);
+        return Board::new(&Puzzle::parse("test", &text).unwrap(), &[]).unwrap();
+    }
+
+    fn hint_for(board: &Board, text: &str) -> Hint {
+        return hint(board, &position(board, text).unwrap(), &Options::default());
+    }
+
+    fn error(board: &Board, text: &str) -> String {
+        match position(board, text) {
+            Err(Error::BadPosition(s)) => return s,
+            res => panic!("expected a bad position, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let board = dominoes();
+        let placed = position(&board, "// a comment\na.....\na...bb\n").unwrap();
+        assert_eq!(placed.iter().map(|p| (p.piece, p.mask)).collect::<Vec<_>>(),
+                   vec![(0, board.bit(0, 0) | board.bit(1, 0)), (1, board.bit(1, 4) | board.bit(1, 5))]);
+        assert_eq!(error(&board, "......\n"), "expected 2 rows, got 1");
+        assert_eq!(error(&board, "// two\n......\n.....\n"),
+                   "line 3 has 5 characters, which don't make the 6 cells of row 2");
+        assert_eq!(error(&board, "......\n....x.\n"), "row 2 column 5: no piece is drawn as x");
+        assert_eq!(error(&board, "a.a...\n......\n"), "the cells drawn as a aren't the piece a");
+
+        let puzzle = Puzzle::parse("test", "[board]\n#..\n[pieces]\naa\n").unwrap();
+        let board = Board::new(&puzzle, &[]).unwrap();
+        assert_eq!(error(&board, "aa.\n"), "row 1 column 1: a on a cell that can't be covered");
+        let puzzle = Puzzle::parse("test", "[board]\n....\n[pieces]\naa\n\npiece b a\nbb\n").unwrap();
+        let board = Board::new(&puzzle, &[]).unwrap();
+        assert_eq!(error(&board, "....\n"), "more than one piece is drawn as a");
+        let puzzle = Puzzle::parse("test", "[board]\n....\n[pieces]\npiece dot .\naa\n\nbb\n").unwrap();
+        let board = Board::new(&puzzle, &[]).unwrap();
+        assert_eq!(error(&board, "....\n"), "piece dot is drawn as '.', the same as a free cell");
+    }
+
+    #[test]
+    fn hints() {
+        let board = dominoes();
+        match hint_for(&board, "......\n......\n") {
+            Hint::Place(p) => assert_eq!(p.mask.trailing_zeros(), 0),
+            h => panic!("expected a placement, got {:?}", h),
+        }
+        // The first free cell is the third one.
+        match hint_for(&board, "aa....\n......\n") {
+            Hint::Place(p) => assert_eq!((p.mask.trailing_zeros(), p.piece == 0), (2, false)),
+            h => panic!("expected a placement, got {:?}", h),
+        }
+        assert!(matches!(hint_for(&board, "aabbcc\nddeeff\n"), Hint::Solved));
+        // (1, 0) is cut off; taking away a or b frees it.
+        assert!(matches!(hint_for(&board, "aa....\n.bb...\n"), Hint::DeadEnd(c) if c == vec![0, 1]));
+        // Two cells cut off, so one piece isn't enough.
+        assert!(matches!(hint_for(&board, "aa..cc\n.bbdd.\n"), Hint::DeadEnd(c) if c.is_empty()));
+        let board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        let options = Options { timeout: Some(Duration::ZERO), ..Options::default() };
+        assert!(matches!(hint(&board, &[], &options), Hint::Unknown));
+    }
+
+    #[test]
+    fn target_labels() {
+        // The emoji style draws targets as two character labels.
+        let board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        let mut solution = None;
+        board.clone().solutions(&Options::default(), |s| {
+            solution = Some(s.clone());
+            return ControlFlow::Break(());
+        });
+        let drawn = text::render(&board, &solution.unwrap(), text::Style::Emoji);
+        assert!(drawn.starts_with("01"));
+        assert_eq!(position(&board, &drawn).unwrap().len(), board.pieces.len());
+        // As is a hint's board.
+        let Hint::Place(p) = hint(&board, &[], &Options::default()) else { panic!("expected a placement") };
+        let drawn = text::render(&board, &board.fill(&[p]), text::Style::Emoji);
+        assert_eq!(position(&board, &drawn).unwrap().iter().map(|q| q.mask).collect::<Vec<_>>(), vec![p.mask]);
+
+        // A label's padding may be missing at the end of a line.
+        let puzzle = Puzzle::parse("test", "[board]\n...\n[pieces]\naa\n[labels]\nA 0 2\nBC 0 0\n").unwrap();
+        let board = Board::new(&puzzle, &["A"]).unwrap();
+        assert_eq!(rows(&board, "aaA\n").unwrap(), vec![vec!['a', 'a', 'A']]);
+        let board = Board::new(&puzzle, &["BC"]).unwrap();
+        assert_eq!(rows(&board, "BCaa\n").unwrap(), vec![vec![LABEL, 'a', 'a']]);
+        assert_eq!(rows(&board, ".aa\n").unwrap(), vec![vec!['.', 'a', 'a']]);
+        // Any label reads as a free cell, target or not.
+        let board = Board::new(&puzzle, &["A"]).unwrap();
+        assert_eq!(rows(&board, "BCaa\n").unwrap(), vec![vec![LABEL, 'a', 'a']]);
+    }
+}
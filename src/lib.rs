@@ -0,0 +1,22 @@
+#![allow(clippy::needless_return)]
+
+mod board;
+mod budget;
+pub mod date;
+pub mod db;
+mod dlx;
+mod error;
+pub mod hint;
+mod piece;
+pub mod play;
+mod puzzle;
+pub mod svg;
+pub mod symmetry;
+pub mod text;
+pub mod verify;
+
+pub use board::{Board, Mask, Options, Outcome, Placement, Solution, Solver, Strategy};
+pub use error::Error;
+pub use piece::{Cell, Motion, Piece};
+pub use puzzle::{Puzzle, BOARD, BOARD_LABELS, PIECES, WEEKDAY_BOARD, WEEKDAY_BOARD_LABELS,
+    WEEKDAY_PIECES};
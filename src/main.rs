@@ -1,223 +1,842 @@
-use std::collections::HashSet;
-use std::hash::Hash;
-use clap::Parser;
-use itertools;
+#![allow(clippy::needless_return)]
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone)]
-struct Piece {
-    id: char,
-    data: Vec<Vec<char>>,
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use chrono::{Datelike, Local, NaiveDate, TimeDelta};
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+
+use a_puzzle_a_day::{date, hint, play, svg, text, verify, Board, Cell, Error, Motion, Options, Outcome, Puzzle, Solution, Solver, Strategy};
+use a_puzzle_a_day::db::{Db, Index};
+use a_puzzle_a_day::hint::Hint;
+use a_puzzle_a_day::text::Style;
+
+/// Solves the A-Puzzle-A-Day calendar puzzle for a date, and puzzles like
+/// it read from a file.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    solve: Args,
 }
 
-impl Piece {
-    fn width(&self) -> usize {
-        return self.data[0].len();
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Count the solutions of every date of the year.
+    All(AllArgs),
+    /// Solve the puzzle by hand.
+    Play(PlayArgs),
+    /// Suggest the next piece to place in a partly solved board.
+    Hint(HintArgs),
+    /// Check a filled in board.
+    Verify(VerifyArgs),
+    /// Save every solution for every date, for --db.
+    BuildDb(BuildDbArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// The boards as drawn by --style.
+    Text,
+    /// One JSON object per line: each solution, then a summary.
+    Json,
+    /// A sheet of all the solutions, or one file each with `--out-dir`.
+    Svg,
+}
+
+#[derive(clap::Args, Debug)]
+struct SearchArgs {
+    /// The search algorithm.
+    #[arg(long, value_enum, default_value_t = Solver::Dlx)]
+    solver: Solver,
+
+    /// How --solver dfs branches; piece by default.
+    #[arg(long, value_enum)]
+    strategy: Option<Strategy>,
+
+    /// Skip branches that leave a region no set of pieces can fill. Only
+    /// for --solver dfs.
+    #[arg(long)]
+    prune: bool,
 
-    fn height(&self) -> usize {
-        return self.data.len();
+    /// Threads to search with.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: usize,
+
+    /// Stop searching after SECS seconds.
+    #[arg(long, value_name = "SECS", value_parser = parse_secs)]
+    timeout: Option<Duration>,
+
+    /// Only one solution out of each set of rotations and reflections of
+    /// each other, when the board is symmetric.
+    #[arg(long)]
+    distinct: bool,
+
+    #[command(flatten)]
+    motion: MotionArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct MotionArgs {
+    /// Pieces may only be rotated, not turned over.
+    #[arg(long, conflicts_with = "fixed")]
+    no_flip: bool,
+
+    /// Pieces may be neither rotated nor turned over.
+    #[arg(long)]
+    fixed: bool,
+}
+
+impl MotionArgs {
+    fn restrict(&self, puzzle: &mut Puzzle) {
+        if self.fixed {
+            puzzle.restrict(Motion::Fixed);
+        } else if self.no_flip {
+            puzzle.restrict(Motion::NoFlip);
+        }
     }
+}
 
-    fn coords(&self) -> itertools::Product<std::ops::Range<usize>, std::ops::Range<usize>> {
-        return itertools::iproduct!(0..self.height(), 0..self.width());
+// The puzzle in `path`, or the built-in one with or without weekday cells,
+// its pieces moving as `motion` allows.
+fn load_puzzle(path: Option<&Path>, weekday: bool, motion: &MotionArgs) -> Result<Puzzle, Error> {
+    let mut puzzle = match path {
+        Some(path) => Puzzle::load(path)?,
+        None if weekday => Puzzle::weekday(),
+        None => Puzzle::month_day(),
+    };
+    motion.restrict(&mut puzzle);
+    return Ok(puzzle);
+}
+
+impl SearchArgs {
+    fn options(&self, limit: Option<usize>) -> Result<Options, Error> {
+        if self.solver == Solver::Dlx && self.strategy.is_some() {
+            return Err(Error::NeedsDfs("--strategy"));
+        }
+        if self.solver == Solver::Dlx && self.prune {
+            return Err(Error::NeedsDfs("--prune"));
+        }
+        return Ok(Options {
+            solver: self.solver,
+            strategy: self.strategy.unwrap_or(Strategy::Piece),
+            prune: self.prune,
+            threads: self.threads,
+            limit,
+            timeout: self.timeout,
+            distinct: self.distinct,
+        });
     }
+}
+
+#[derive(clap::Args, Debug)]
+struct AllArgs {
+    /// Solve the edition with weekdays.
+    #[arg(long)]
+    weekday: bool,
+
+    /// Read the puzzle from FILE.
+    #[arg(long, value_name = "FILE")]
+    puzzle: Option<PathBuf>,
+
+    /// The calendar used for the weekdays. Defaults to the current year.
+    #[arg(long)]
+    year: Option<i32>,
+
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildDbArgs {
+    /// Where to write the database.
+    #[arg(long, value_name = "FILE")]
+    out: PathBuf,
+
+    /// Every weekday of every date, so the database works for any year.
+    #[arg(long)]
+    weekday: bool,
+
+    /// Read the puzzle from FILE.
+    #[arg(long, value_name = "FILE")]
+    puzzle: Option<PathBuf>,
+
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+// The puzzle and the cells to leave free.
+#[derive(clap::Args, Debug)]
+struct PuzzleArgs {
+    /// Day of the month to solve, today's by default.
+    #[arg(short, long)]
+    day: Option<usize>,
+
+    /// Month to solve, 1 to 12, today's by default.
+    #[arg(short, long)]
+    month: Option<usize>,
+
+    /// Date to solve, as YYYY-MM-DD or MM-DD.
+    #[arg(long, conflicts_with_all = ["day", "month"])]
+    date: Option<String>,
+
+    /// Solve the day after.
+    #[arg(long, conflicts_with = "offset")]
+    tomorrow: bool,
 
-    fn from(s: &[&str]) -> Piece {
-        let res = s[0].find(|c| c != '.').unwrap();
-        let mut res = Piece {
-            id: s[0].chars().nth(res).unwrap() as char,
-            data: vec![],
+    /// Solve this many days later, or earlier if negative.
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    offset: i64,
+
+    /// Accept dates like 31 April that the board has cells for.
+    #[arg(long)]
+    allow_impossible: bool,
+
+    /// Solve the edition with weekdays. Without a value, the weekday of the
+    /// date being solved.
+    #[arg(long, num_args = 0..=1, value_parser = parse_weekday)]
+    weekday: Option<Option<usize>>,
+
+    /// Read the puzzle from FILE.
+    #[arg(long, value_name = "FILE")]
+    puzzle: Option<PathBuf>,
+
+    /// Cover the cells with these labels instead of a date.
+    #[arg(long, value_name = "LABEL", num_args = 1.., value_delimiter = ',',
+          conflicts_with_all = ["day", "month", "date", "tomorrow", "offset", "weekday"])]
+    cover: Vec<String>,
+}
+
+impl PuzzleArgs {
+    // The puzzle with the labels of its target cells. Puzzles without
+    // labels are solved with every cell covered.
+    fn load(&self, motion: &MotionArgs) -> Result<(Puzzle, Vec<String>), Error> {
+        let puzzle = load_puzzle(self.puzzle.as_deref(), self.weekday.is_some(), motion)?;
+        let targets = match (self.cover.is_empty(), puzzle.labels.is_empty()) {
+            (false, _) => self.cover.clone(),
+            (true, false) => date_targets(self, &puzzle)?,
+            (true, true) if self.weekday.is_some() => return Err(Error::NoWeekdayCells),
+            (true, true) => vec![],
         };
-        for line in s {
-            res.data.push(line.chars().collect());
+        return Ok((puzzle, targets));
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct PlayArgs {
+    #[command(flatten)]
+    target: PuzzleArgs,
+
+    #[command(flatten)]
+    motion: MotionArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct HintArgs {
+    /// The board so far, one character per cell: '.' for a free cell and a
+    /// piece's glyph for the cells it covers. "-" reads standard input.
+    #[arg(long, value_name = "FILE")]
+    position: PathBuf,
+
+    #[command(flatten)]
+    target: PuzzleArgs,
+
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// The filled in board, drawn the same way as for hint. "-" reads
+    /// standard input.
+    #[arg(long, value_name = "FILE")]
+    solution: PathBuf,
+
+    #[command(flatten)]
+    target: PuzzleArgs,
+
+    #[command(flatten)]
+    motion: MotionArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct Args {
+    #[command(flatten)]
+    target: PuzzleArgs,
+
+    #[command(flatten)]
+    search: SearchArgs,
+
+    /// How to write the solutions.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// How text output draws the board.
+    #[arg(long, value_enum, default_value_t = Style::Emoji)]
+    style: Style,
+
+    /// Solutions per row of an SVG sheet.
+    #[arg(long, default_value_t = 4)]
+    columns: usize,
+
+    /// Write each SVG solution to its own file in DIR. Needs --format svg.
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// Only print the number of solutions.
+    #[arg(long)]
+    count: bool,
+
+    /// Stop at the first solution.
+    #[arg(long, conflicts_with = "limit")]
+    first: bool,
+
+    /// Stop after this many solutions.
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// With --count, also print the search statistics.
+    #[arg(long, requires = "count")]
+    calls: bool,
+
+    /// Look the solutions up in a database from build-db instead of
+    /// searching.
+    #[arg(long, value_name = "FILE")]
+    db: Option<PathBuf>,
+}
+
+fn solution_json(board: &Board, n: usize, s: &Solution) -> Value {
+    let placements: Vec<Value> = s.placements.iter().map(|p| {
+        let piece = &board.pieces[p.piece][p.orientation];
+        let cells: Vec<[usize; 2]> = piece.filled().map(|(r, c)| [p.row + r, p.col + c]).collect();
+        return json!({
+            "piece": p.piece,
+            "name": piece.name,
+            "glyph": piece.glyph.to_string(),
+            "orientation": p.orientation,
+            "row": p.row,
+            "col": p.col,
+            "cells": cells,
+        });
+    }).collect();
+    // The index of the covering piece, {"target": LABEL}, "blocked", or
+    // null for an empty cell.
+    let grid: Vec<Vec<Value>> = s.grid.iter().map(|row| row.iter().map(|cell| match cell {
+        Cell::Empty => Value::Null,
+        Cell::Blocked => json!("blocked"),
+        Cell::Target(label) => json!({ "target": label }),
+        Cell::Piece(i) => json!(i),
+    }).collect()).collect();
+    return json!({
+        "type": "solution", "index": n, "class": s.class, "placements": placements, "grid": grid,
+    });
+}
+
+fn summary_json(puzzle: &Puzzle, board: &Board, options: &Options, n: usize, outcome: Outcome,
+                elapsed: Duration) -> Value {
+    let targets: Vec<Value> = board.targets.iter()
+        .map(|(label, r, c)| json!({ "label": label, "row": r, "col": c }))
+        .collect();
+    let mut res = json!({
+        "type": "summary",
+        "puzzle": puzzle.name,
+        "targets": targets,
+        "solutions": n,
+        "calls": board.calls,
+        "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+        "search": describe(outcome),
+    });
+    if options.prune {
+        res["pruned"] = json!(board.pruned);
+    }
+    return res;
+}
+
+fn parse_secs(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|_| format!("not a number: {}", s))?;
+    return Duration::try_from_secs_f64(secs).map_err(|e| e.to_string());
+}
+
+fn describe(outcome: Outcome) -> &'static str {
+    return match outcome {
+        Outcome::Complete => "complete",
+        Outcome::Stopped => "stopped",
+        Outcome::LimitReached => "truncated at the solution limit",
+        Outcome::TimedOut => "truncated by the timeout",
+    };
+}
+
+fn print_stats(board: &Board, options: &Options, outcome: Outcome) {
+    println!("Calls: {}", board.calls);
+    if options.prune {
+        println!("Pruned: {}", board.pruned);
+    }
+    println!("Search: {}", describe(outcome));
+}
+
+fn parse_weekday(s: &str) -> Result<usize, String> {
+    return date::parse_weekday(s).map_err(|e| e.to_string());
+}
+
+// `--date`, or today with `--day` and `--month` replacing its parts,
+// moved by `--offset` days. A date without a year is in the current one.
+// The full date is unknown if it doesn't exist in that year, like
+// 29 February in most years.
+fn target_date(args: &PuzzleArgs) -> Result<(usize, usize, Option<NaiveDate>), Error> {
+    let today = Local::now().date_naive();
+    let (year, month, day) = match &args.date {
+        Some(s) => date::parse(s)?,
+        None => (None, today.month() as usize, today.day() as usize),
+    };
+    let year = year.unwrap_or(today.year());
+    let day = args.day.unwrap_or(day);
+    let month = args.month.unwrap_or(month);
+    let full = u32::try_from(month).ok().zip(u32::try_from(day).ok())
+        .and_then(|(m, d)| NaiveDate::from_ymd_opt(year, m, d));
+    let offset = if args.tomorrow { 1 } else { args.offset };
+    if offset == 0 {
+        return Ok((day, month, full));
+    }
+    date::check_date(day, month)?;
+    let res = full.ok_or(Error::NotInYear { day, month, year })?;
+    let res = res.checked_add_signed(TimeDelta::try_days(offset).unwrap_or(TimeDelta::MAX))
+        .ok_or(Error::BadOffset(offset))?;
+    return Ok((res.day() as usize, res.month() as usize, Some(res)));
+}
+
+// The labels of the cells to cover for the date given on the command line.
+fn date_targets(args: &PuzzleArgs, puzzle: &Puzzle) -> Result<Vec<String>, Error> {
+    let (day, month, full) = target_date(args)?;
+    if args.allow_impossible {
+        date::check_range(day, month)?;
+    } else {
+        date::check_date(day, month)?;
+    }
+    let mut res = vec![date::month_label(month)?, date::day_label(day)];
+    if !puzzle.has_weekdays() {
+        if args.weekday.is_some() {
+            return Err(Error::NoWeekdayCells);
         }
-        return res;
+    } else if let Some(Some(w)) = args.weekday {
+        res.push(date::weekday_label(w)?);
+    } else {
+        let w = full.ok_or(Error::UnknownWeekday)?.weekday().num_days_from_sunday();
+        res.push(date::weekday_label(w as usize)?);
     }
+    return Ok(res);
+}
 
-    #[allow(dead_code)]
-    fn print(&self) {
-        for r in &self.data {
-            for c in r {
-                print!("{}", c);
+fn run(args: &Args) -> Result<(), Error> {
+    if args.out_dir.is_some() && args.format != Format::Svg {
+        return Err(Error::NeedsSvg("--out-dir"));
+    }
+    let (puzzle, targets) = args.target.load(&args.search.motion)?;
+    let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+    let mut board = Board::new(&puzzle, &targets)?;
+    let options = args.search.options(if args.first { Some(1) } else { args.limit })?;
+    let start = Instant::now();
+    if let Some(path) = &args.db {
+        let mut db = Index::open(path)?;
+        db.check(&puzzle)?;
+        db.load(&mut board)?;
+    }
+    if args.count {
+        let (n, outcome) = board.count(&options);
+        if args.format == Format::Json {
+            println!("{}", summary_json(&puzzle, &board, &options, n, outcome, start.elapsed()));
+            return Ok(());
+        }
+        println!("{}", n);
+        if args.calls {
+            print_stats(&board, &options, outcome);
+        } else if outcome != Outcome::Complete {
+            eprintln!("warning: search {}", describe(outcome));
+        }
+        return Ok(());
+    }
+    if let Some(dir) = &args.out_dir {
+        std::fs::create_dir_all(dir).map_err(|e| Error::Io(format!("{}: {}", dir.display(), e)))?;
+    }
+    // A copy for use while the search holds the board.
+    let shown = board.clone();
+    let mut n = 0;
+    let mut total = 0;
+    let mut sheet = vec![];
+    let mut failed = None;
+    let outcome = board.solutions(&options, |s| {
+        n += 1;
+        total += s.class;
+        match (args.format, &args.out_dir) {
+            (Format::Text, _) if options.distinct => {
+                println!("#{} ({} symmetric):", n, s.class);
+                print!("{}", text::render(&shown, s, args.style));
+            }
+            (Format::Text, _) => {
+                println!("#{}:", n);
+                print!("{}", text::render(&shown, s, args.style));
+            }
+            (Format::Json, _) => println!("{}", solution_json(&shown, n, s)),
+            (Format::Svg, None) => sheet.push(s.clone()),
+            (Format::Svg, Some(dir)) => {
+                let path = dir.join(format!("solution-{:04}.svg", n));
+                if let Err(e) = std::fs::write(&path, svg::render(&shown, s)) {
+                    failed = Some(Error::Io(format!("{}: {}", path.display(), e)));
+                    return ControlFlow::Break(());
+                }
             }
-            println!("");
         }
+        return ControlFlow::Continue(());
+    });
+    if let Some(e) = failed {
+        return Err(e);
     }
-
-    fn rev(&self) -> Piece {
-        let mut res = Piece {
-            id: self.id,
-            data: vec![],
-        };
-        for r in &self.data {
-            res.data.push(r.clone());
-            res.data.last_mut().unwrap().reverse();
+    match args.format {
+        Format::Text => {
+            if options.distinct {
+                println!("Distinct: {} of {}", n, total);
+            }
+            print_stats(&board, &options, outcome);
+        }
+        Format::Json => {
+            let mut summary = summary_json(&puzzle, &board, &options, n, outcome, start.elapsed());
+            if options.distinct {
+                summary["total"] = json!(total);
+            }
+            println!("{}", summary);
+        }
+        Format::Svg => {
+            if args.out_dir.is_none() {
+                print!("{}", svg::sheet(&shown, &sheet, args.columns));
+            }
+            eprintln!("Solutions: {}", n);
+            eprintln!("Calls: {}", board.calls);
+            eprintln!("Search: {}", describe(outcome));
         }
-        return res;
     }
+    return Ok(());
+}
 
-    fn transpose(&self) -> Piece {
-        let mut res = Piece {
-            id: self.id,
-            data: vec![],
-        };
-        for c in 0..self.width() {
-            let mut row = vec![];
-            for r in 0..self.height() {
-                row.push(self.data[r][c]);
+fn short_date(day: usize, month: usize, full: Option<NaiveDate>) -> String {
+    let name = &date::month_name(month).unwrap_or("???")[..3];
+    return match full {
+        Some(d) => format!("{} {} {}", &date::WEEKDAYS[d.weekday().num_days_from_sunday() as usize][..3],
+                           name, day),
+        None => format!("{} {}", name, day),
+    };
+}
+
+// Moves `board` to `targets`, making it on first use, so that a run over
+// many targets only works out the placements once.
+fn retarget<'a>(board: &'a mut Option<Board>, puzzle: &Puzzle, targets: &[&str]) -> Result<&'a mut Board, Error> {
+    return match board {
+        Some(b) => { b.set_targets(puzzle, targets)?; Ok(b) }
+        None => Ok(board.insert(Board::new(puzzle, targets)?)),
+    };
+}
+
+// Every date solved on one board, so the placements are only worked out
+// once. Dates with weekdays follow the calendar of `--year`.
+fn run_all(args: &AllArgs) -> Result<(), Error> {
+    let puzzle = load_puzzle(args.puzzle.as_deref(), args.weekday, &args.search.motion)?;
+    let weekdays = puzzle.has_weekdays();
+    let year = args.year.unwrap_or(Local::now().year());
+    let options = args.search.options(None)?;
+    let start = Instant::now();
+
+    let mut board: Option<Board> = None;
+    // (day, month, full date, count, outcome) for every date solved.
+    let mut results = vec![];
+    for month in 1..=12 {
+        for day in 1..=date::days_in_month(month) {
+            let mut targets = vec![date::month_label(month)?, date::day_label(day)];
+            let full = NaiveDate::from_ymd_opt(year, month as u32, day as u32);
+            if weekdays {
+                let Some(full) = full else { continue };
+                targets.push(date::weekday_label(full.weekday().num_days_from_sunday() as usize)?);
             }
-            res.data.push(row);
+            let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+            let board = retarget(&mut board, &puzzle, &targets)?;
+            let (n, outcome) = board.count(&options);
+            results.push((day, month, full.filter(|_| weekdays), n, outcome));
         }
-        return res;
     }
+    let elapsed = start.elapsed();
 
-    fn rotate(&self) -> Piece {
-        return self.rev().transpose();
+    let cell = |n: usize, outcome: Outcome| {
+        return if outcome == Outcome::Complete { n.to_string() } else { format!("{}+", n) };
+    };
+    let width = results.iter().map(|r| cell(r.3, r.4).len()).max().unwrap_or(1).max(2);
+    print!("   ");
+    for day in 1..=31 {
+        print!(" {:>width$}", day);
     }
-
-    fn generate_positions(&self) -> HashSet<Piece> {
-        let mut res = HashSet::new();
-        let rev = self.rev();
-        for p in vec![self, &rev] {
-            let mut q = p.clone();
-            for _ in 0..4 {
-                let r = q.rotate();
-                res.insert(q);
-                q = r;
+    println!();
+    for month in 1..=12 {
+        print!("{}", &date::month_name(month)?[..3]);
+        for day in 1..=31 {
+            match results.iter().find(|r| (r.0, r.1) == (day, month)) {
+                Some(r) => print!(" {:>width$}", cell(r.3, r.4)),
+                None => print!(" {:>width$}", ""),
             }
         }
-        return res;
+        println!();
     }
+    println!();
 
-    fn fit(&self, b: &Piece, r: usize, c: usize) -> Vec<(usize, usize)> {
-        let mut res = vec![];
-        if r + self.height() > b.height() || c + self.width() > b.width() {
-            return res;
-        }
-        for (pr, pc) in self.coords() {
-            let rr = r + pr;
-            let cc = c + pc;
-            if self.data[pr][pc] != '.' {
-                if b.data[rr][cc] != '.' {
-                    return vec![];
-                }
-                else {
-                    res.push((rr, cc));
-                }
+    let dates = |n: usize| {
+        return results.iter().filter(|r| r.3 == n && r.4 == Outcome::Complete)
+            .map(|r| short_date(r.0, r.1, r.2))
+            .collect::<Vec<_>>()
+            .join(", ");
+    };
+    let total: usize = results.iter().map(|r| r.3).sum();
+    println!("Dates: {}", results.len());
+    println!("Solutions: {}", total);
+    if let Some(min) = results.iter().map(|r| r.3).filter(|&n| n > 0).min() {
+        println!("Fewest: {} ({})", min, dates(min));
+    }
+    if let Some(max) = results.iter().map(|r| r.3).max() {
+        println!("Most: {} ({})", max, dates(max));
+    }
+    println!("Unsolvable: {}", if dates(0).is_empty() { "none".to_string() } else { dates(0) });
+    let unfinished: Vec<_> = results.iter().filter(|r| r.4 != Outcome::Complete)
+        .map(|r| short_date(r.0, r.1, r.2))
+        .collect();
+    if !unfinished.is_empty() {
+        println!("Timed out: {}", unfinished.join(", "));
+    }
+    println!("Time: {:.2?}", elapsed);
+    return Ok(());
+}
+
+fn run_build_db(args: &BuildDbArgs) -> Result<(), Error> {
+    let puzzle = load_puzzle(args.puzzle.as_deref(), args.weekday, &args.search.motion)?;
+    let options = args.search.options(None)?;
+    let start = Instant::now();
+
+    // Every date the board has cells for, 31 April included so that
+    // --allow-impossible finds it too, or just the one board for puzzles
+    // without labels.
+    let mut all_targets = vec![];
+    for month in 1..=12 {
+        for day in 1..=31 {
+            let targets = vec![date::month_label(month)?, date::day_label(day)];
+            if !puzzle.has_weekdays() {
+                all_targets.push(targets);
+                continue;
+            }
+            for w in 0..7 {
+                all_targets.push([targets.clone(), vec![date::weekday_label(w)?]].concat());
             }
         }
-        return res;
+    }
+    if puzzle.labels.is_empty() {
+        all_targets = vec![vec![]];
     }
 
+    let mut db = Db::new(&puzzle);
+    let mut board: Option<Board> = None;
+    let mut total = 0;
+    for targets in &all_targets {
+        let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+        let board = retarget(&mut board, &puzzle, &targets)?;
+        total += db.add(board, &options)?;
+    }
+    db.write(&args.out)?;
+    println!("Entries: {}", db.entries.len());
+    println!("Solutions: {}", total);
+    println!("Checksum: {:016x}", db.checksum);
+    println!("Time: {:.2?}", start.elapsed());
+    return Ok(());
 }
 
-const PIECES : [&[&str]; 8]  = [
-    &[ "🟥..", "🟥..", "🟥🟥🟥" ],
-    &[ "🟦🟦🟦🟦", ".🟦.." ],
-    &[ "🟧🟧..", ".🟧🟧🟧" ],
-    &[ "🟨🟨🟨", "🟨🟨🟨" ],
-    &[ "🟩..", "🟩🟩🟩", "..🟩" ],
-    &[ "🟪...", "🟪🟪🟪🟪" ],
-    &[ "🟫.🟫", "🟫🟫🟫" ],
-    &[ "⬜⬜.", "⬜⬜⬜" ]
-];
+fn run_play(args: &PlayArgs) -> Result<(), Error> {
+    let (puzzle, targets) = args.target.load(&args.motion)?;
+    let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+    let board = Board::new(&puzzle, &targets)?;
+    return play::run(&puzzle, &board);
+}
 
-const BOARD : [&str; 7] = [
-    "......⬛",
-    "......⬛",
-    ".......",
-    ".......",
-    ".......",
-    ".......",
-    "...⬛⬛⬛⬛",
-];
+// A board drawn in a file, or on standard input for "-".
+fn read_drawing(path: &Path) -> Result<String, Error> {
+    let read = if path.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(path)
+    };
+    return read.map_err(|e| Error::Io(format!("{}: {}", path.display(), e)));
+}
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long)]
-    day: usize,
+fn run_hint(args: &HintArgs) -> Result<(), Error> {
+    let (puzzle, targets) = args.target.load(&args.search.motion)?;
+    let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+    let board = Board::new(&puzzle, &targets)?;
+    let position = read_drawing(&args.position)?;
+    let mut placed = hint::position(&board, &position)?;
+    let name = |i: usize| board.pieces[i][0].name.clone();
+    match hint::hint(&board, &placed, &args.search.options(None)?) {
+        Hint::Solved => println!("Solved."),
+        // The board goes to stdout by itself, ready to be read back in.
+        Hint::Place(p) => {
+            eprintln!("Place {}:", name(p.piece));
+            placed.push(p);
+            print!("{}", text::render(&board, &board.fill(&placed), Style::Emoji));
+        }
+        Hint::DeadEnd(culprits) if culprits.is_empty() => {
+            println!("Dead end: no single piece can be taken away to fix it.");
+        }
+        Hint::DeadEnd(culprits) => {
+            let names: Vec<String> = culprits.into_iter().map(name).collect();
+            println!("Dead end: take away {}.", names.join(" or "));
+        }
+        Hint::Unknown => println!("No hint: the search timed out."),
+    }
+    return Ok(());
+}
 
-    #[arg(short, long)]
-    month: usize,
+fn run_verify(args: &VerifyArgs) -> Result<(), Error> {
+    let (puzzle, targets) = args.target.load(&args.motion)?;
+    let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+    let board = Board::new(&puzzle, &targets)?;
+    let problems = verify::problems(&board, &read_drawing(&args.solution)?)?;
+    if problems.is_empty() && targets.is_empty() {
+        println!("Valid solution.");
+        return Ok(());
+    }
+    if problems.is_empty() {
+        println!("Valid solution for {}.", targets.join(" "));
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    return Err(Error::NotASolution(problems.len()));
 }
 
-struct Board {
-    pieces: Vec<Vec<Piece>>,
-    board: Piece,
-    day: usize,
-    month: usize,
-    n: usize,
-    calls: usize,
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let res = match &cli.command {
+        None => run(&cli.solve),
+        Some(Command::All(args)) => run_all(args),
+        Some(Command::Play(args)) => run_play(args),
+        Some(Command::Hint(args)) => run_hint(args),
+        Some(Command::Verify(args)) => run_verify(args),
+        Some(Command::BuildDb(args)) => run_build_db(args),
+    };
+    if let Err(e) = res {
+        eprintln!("error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    return ExitCode::SUCCESS;
 }
 
-impl Board {
-    fn new(args: &Args) -> Board {
-        let mut board = Piece::from(&BOARD);
-        let mut pieces = vec![];
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for p in &PIECES {
-            let piece = Piece::from(p);
-            let pos : Vec<Piece> = piece.generate_positions().into_iter().collect();
-            pieces.push(pos);
-        }
+    fn target(args: &str) -> Result<(usize, usize), Error> {
+        let cli = Cli::parse_from(format!("apad {}", args).split_whitespace());
+        let (day, month, _) = target_date(&cli.solve.target)?;
+        return Ok((day, month));
+    }
 
-        let d = args.day - 1;
-        let m = args.month - 1;
-        board.data[m / 6][m % 6] = 'M';
-        board.data[2 + d / 7][d % 7] = 'D';
-        return Board { pieces, board,
-            day: args.day, month: args.month, n: 1, calls: 0 };
+    #[test]
+    fn target_dates() {
+        assert_eq!(target("--date 2026-10-19"), Ok((19, 10)));
+        assert_eq!(target("--date 2026-10-19 --offset -19"), Ok((30, 9)));
+        assert_eq!(target("--date 2026-12-31 --tomorrow"), Ok((1, 1)));
+        assert_eq!(target("--date 2024-02-28 --tomorrow"), Ok((29, 2)));
+        assert_eq!(target("--date 2026-02-28 --tomorrow"), Ok((1, 3)));
+        assert_eq!(target("-d 31 -m 4"), Ok((31, 4)));
+        assert_eq!(target("-d 31 -m 4 --tomorrow"), Err(Error::ImpossibleDate { day: 31, month: 4 }));
+        assert_eq!(target("--date 2026-02-30"), Err(Error::BadDate("2026-02-30".to_string())));
+        assert_eq!(target("--date 2026-10-19 --offset 9999999999999"), Err(Error::BadOffset(9999999999999)));
     }
 
-    fn print(&self) {
-        for r in &self.board.data {
-            for c in r {
-                match c {
-                    'M' => print!("{:0>2}", self.month),
-                    'D' => print!("{:0>2}", self.day),
-                    _   => print!("{}", c),
-                }
-            }
-            println!("");
-        }
+    #[test]
+    fn impossible_dates() {
+        let targets = |args: &str| {
+            let cli = Cli::parse_from(format!("apad {}", args).split_whitespace());
+            return date_targets(&cli.solve.target, &Puzzle::month_day());
+        };
+        assert_eq!(targets("--date 04-31 --allow-impossible"), Ok(vec!["APR".to_string(), "31".to_string()]));
+        assert_eq!(targets("--date 04-31"), Err(Error::ImpossibleDate { day: 31, month: 4 }));
     }
 
-    fn _solve_dfs(&mut self, pieces: &Vec<Vec<Piece>>, piece_id: usize) {
-        self.calls += 1;
-        if piece_id == self.pieces.len() {
-            println!("#{}:", self.n);
-            self.print();
-            self.n += 1;
-            return;
-        }
-        for (r, c) in self.board.coords() {
-            for p in &pieces[piece_id] {
-                let occ = &p.fit(&self.board, r, c);
-                if occ.len() == 0 {
-                    continue;
-                }
-                for &(rr, cc) in occ.iter() {
-                    self.board.data[rr][cc] = p.id;
-                }
-                self._solve_dfs(pieces, piece_id + 1);
-                for &(rr, cc) in occ.iter() {
-                    self.board.data[rr][cc] = '.';
-                }
-            }
-        }
+    #[test]
+    fn unlabelled_puzzles() {
+        let path = std::env::temp_dir().join(format!("apad-{}-unlabelled.txt", std::process::id()));
+        std::fs::write(&path, "[board]\n..\n[pieces]\naa\n").unwrap();
+        let load = |args: &str| {
+            let cli = Cli::parse_from(format!("apad --puzzle {} {}", path.display(), args).split_whitespace());
+            return cli.solve.target.load(&cli.solve.search.motion).map(|(_, targets)| targets);
+        };
+        assert_eq!(load(""), Ok(vec![]));
+        assert_eq!(load("--weekday"), Err(Error::NoWeekdayCells));
+        assert_eq!(load("--weekday mon"), Err(Error::NoWeekdayCells));
+        std::fs::remove_file(&path).unwrap();
     }
 
-    fn solve_dfs(&mut self) {
-        self.n = 1;
-        self.calls = 0;
-        self._solve_dfs(&self.pieces.clone(), 0);
-        println!("Calls: {}", self.calls);
+    #[test]
+    fn short_dates() {
+        assert_eq!(short_date(19, 10, NaiveDate::from_ymd_opt(2026, 10, 19)), "Mon Oct 19");
+        assert_eq!(short_date(29, 2, None), "Feb 29");
+    }
+
+    #[test]
+    fn json() {
+        let puzzle = Puzzle::month_day();
+        let mut board = Board::new(&puzzle, &["JAN", "1"]).unwrap();
+        let options = Options { limit: Some(1), ..Options::default() };
+        let shown = board.clone();
+        let mut solution = None;
+        let outcome = board.solutions(&options, |s| {
+            solution = Some(solution_json(&shown, 1, s));
+            return ControlFlow::Continue(());
+        });
+        let solution = solution.unwrap();
+        assert_eq!((&solution["type"], &solution["index"]), (&json!("solution"), &json!(1)));
+        let placements = solution["placements"].as_array().unwrap();
+        assert_eq!(placements.len(), puzzle.pieces.len());
+        let cells: usize = placements.iter().map(|p| p["cells"].as_array().unwrap().len()).sum();
+        assert_eq!(cells, 41);
+        assert_eq!(solution["grid"].as_array().unwrap().len(), 7);
+
+        let summary = summary_json(&puzzle, &board, &options, 1, outcome, Duration::ZERO);
+        assert_eq!(summary["type"], "summary");
+        assert_eq!(summary["puzzle"], "month-day");
+        assert_eq!(summary["targets"][0], json!({ "label": "JAN", "row": 0, "col": 0 }));
+        assert_eq!(summary["solutions"], 1);
+        assert_eq!(summary["search"], "truncated at the solution limit");
+        assert!(summary.get("pruned").is_none());
     }
-}
 
-fn main() {
-    let args = Args::parse();
-    let mut board = Board::new(&args);
-    board.solve_dfs();
-}
\ No newline at end of file
+    #[test]
+    fn search_options() {
+        let options = |args: &str| Cli::parse_from(format!("apad {}", args).split_whitespace()).solve.search.options(None);
+        assert_eq!(options("--strategy cell").err(), Some(Error::NeedsDfs("--strategy")));
+        assert_eq!(options("--prune").err(), Some(Error::NeedsDfs("--prune")));
+        assert_eq!(options("--threads 4").unwrap().threads, 4);
+        assert!(Cli::try_parse_from(["apad", "--threads", "0"]).is_err());
+        assert!(options("--solver dfs --prune").unwrap().prune);
+        let dfs = options("--solver dfs --strategy cell").unwrap();
+        assert!(matches!((dfs.solver, dfs.strategy), (Solver::Dfs, Strategy::Cell)));
+        assert!(matches!(options("--solver dfs").unwrap().strategy, Strategy::Piece));
+    }
+
+    #[test]
+    fn out_dir() {
+        let dir = std::env::temp_dir().join(format!("apad-{}-out-dir", std::process::id()));
+        for format in ["text", "json"] {
+            let cli = Cli::parse_from(["apad", "--format", format, "--out-dir", dir.to_str().unwrap()]);
+            assert_eq!(run(&cli.solve), Err(Error::NeedsSvg("--out-dir")));
+        }
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::Error;
+
+// What a board cell holds. The order is only there to pick one of a set
+// of symmetric solutions.
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone)]
+pub enum Cell {
+    Empty,
+    Blocked,
+    // Left free by the solutions, named by its label.
+    Target(String),
+    // Covered by the piece with this index.
+    Piece(usize),
+}
+
+// How a piece may be turned when placed.
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy)]
+pub enum Motion {
+    // Any rotation, either side up.
+    Free,
+    // Rotations only, as for pieces printed on one side.
+    NoFlip,
+    // As given.
+    Fixed,
+}
+
+// A piece in one orientation. `index` is its position in the puzzle, the
+// same for all orientations; `name` and `glyph` are only for display.
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
+pub struct Piece {
+    pub index: usize,
+    pub name: String,
+    pub glyph: char,
+    pub motion: Motion,
+    pub cells: Vec<Vec<bool>>,
+}
+
+impl Piece {
+    pub fn width(&self) -> usize {
+        return self.cells[0].len();
+    }
+
+    pub fn height(&self) -> usize {
+        return self.cells.len();
+    }
+
+    pub fn size(&self) -> usize {
+        return self.cells.iter().flatten().filter(|&&c| c).count();
+    }
+
+    pub fn coords(&self) -> itertools::Product<std::ops::Range<usize>, std::ops::Range<usize>> {
+        return itertools::iproduct!(0..self.height(), 0..self.width());
+    }
+
+    // Rows of equal length, '.' for empty cells. The glyph is the first
+    // other character, and the name the glyph.
+    pub fn from(index: usize, s: &[&str]) -> Result<Piece, Error> {
+        let glyph = s.iter().flat_map(|r| r.chars()).find(|&c| c != '.').ok_or(Error::EmptyPiece)?;
+        let mut res = Piece {
+            index,
+            name: glyph.to_string(),
+            glyph,
+            motion: Motion::Free,
+            cells: vec![],
+        };
+        for line in s {
+            res.cells.push(line.chars().map(|c| c != '.').collect());
+            if res.cells[0].len() != res.cells.last().unwrap().len() {
+                return Err(Error::RaggedPiece(res.name));
+            }
+        }
+        return Ok(res);
+    }
+
+    // The piece's own cells, as (row, col).
+    pub fn filled(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        return self.coords().filter(|&(r, c)| self.cells[r][c]);
+    }
+
+    pub fn rev(&self) -> Piece {
+        let mut res = Piece {
+            cells: vec![],
+            ..self.clone()
+        };
+        for r in &self.cells {
+            res.cells.push(r.clone());
+            res.cells.last_mut().unwrap().reverse();
+        }
+        return res;
+    }
+
+    pub fn transpose(&self) -> Piece {
+        let mut res = Piece {
+            cells: vec![],
+            ..self.clone()
+        };
+        for c in 0..self.width() {
+            let mut row = vec![];
+            for r in 0..self.height() {
+                row.push(self.cells[r][c]);
+            }
+            res.cells.push(row);
+        }
+        return res;
+    }
+
+    pub fn rotate(&self) -> Piece {
+        return self.rev().transpose();
+    }
+
+    // Distinct orientations allowed by `motion`, in a fixed order so the
+    // search order (and the output) doesn't change between runs. Two
+    // orientations are the same when they cover the same cells; the rest
+    // of the piece is shared.
+    pub fn generate_positions(&self) -> Vec<Piece> {
+        let mut seen: HashSet<Vec<Vec<bool>>> = HashSet::new();
+        let mut res = vec![];
+        let rev = self.rev();
+        let (sides, turns) = match self.motion {
+            Motion::Free => (&[self, &rev][..], 4),
+            Motion::NoFlip => (&[self][..], 4),
+            Motion::Fixed => (&[self][..], 1),
+        };
+        for &p in sides {
+            let mut q = p.clone();
+            for _ in 0..turns {
+                let r = q.rotate();
+                if seen.insert(q.cells.clone()) {
+                    res.push(q);
+                }
+                q = r;
+            }
+        }
+        return res;
+    }
+
+    // The board cells the piece covers with its top left corner at (r, c),
+    // or nothing if it doesn't fit on the empty cells there.
+    pub fn fit(&self, b: &[Vec<Cell>], r: usize, c: usize) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        if r + self.height() > b.len() || c + self.width() > b[0].len() {
+            return res;
+        }
+        for (pr, pc) in self.coords() {
+            let rr = r + pr;
+            let cc = c + pc;
+            if self.cells[pr][pc] {
+                if b[rr][cc] != Cell::Empty {
+                    return vec![];
+                }
+                else {
+                    res.push((rr, cc));
+                }
+            }
+        }
+        return res;
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from() {
+        let p = Piece::from(3, &["..x", "xxx"]).unwrap();
+        assert_eq!((p.index, p.name.as_str(), p.glyph), (3, "x", 'x'));
+        assert_eq!((p.width(), p.height(), p.size()), (3, 2, 4));
+        assert_eq!(p.filled().collect::<Vec<_>>(), vec![(0, 2), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(Piece::from(0, &[]), Err(Error::EmptyPiece));
+        assert_eq!(Piece::from(0, &["...", "xxx"]).map(|p| p.glyph), Ok('x'));
+        assert_eq!(Piece::from(0, &["..", ".."]), Err(Error::EmptyPiece));
+        assert_eq!(Piece::from(0, &["x.", "xxx"]), Err(Error::RaggedPiece("x".to_string())));
+    }
+
+    #[test]
+    fn orientations() {
+        let count = |rows: &[&str]| Piece::from(0, rows).unwrap().generate_positions().len();
+        assert_eq!(count(&["xxx", "xxx"]), 2);
+        assert_eq!(count(&["x..", "xxx", "..x"]), 4);
+        assert_eq!(count(&["x...", "xxxx"]), 8);
+    }
+
+    #[test]
+    fn motions() {
+        let mut p = Piece::from(0, &["x...", "xxxx"]).unwrap();
+        for (motion, n) in [(Motion::Free, 8), (Motion::NoFlip, 4), (Motion::Fixed, 1)] {
+            p.motion = motion;
+            let orientations = p.generate_positions();
+            assert_eq!(orientations.len(), n, "{:?}", motion);
+            assert_eq!(orientations[0], p);
+        }
+    }
+}
@@ -0,0 +1,405 @@
+// Solving a puzzle by hand in the terminal. The selected piece floats
+// over the board until it's dropped; it's drawn red where it doesn't fit.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::board::Board;
+use crate::piece::{Cell, Motion, Piece};
+use crate::puzzle::Puzzle;
+use crate::svg;
+use crate::text;
+use crate::Error;
+
+const HELP: [&str; 4] = [
+    "arrows move   r rotate   f flip   space drop/lift",
+    "tab/shift-tab or 1-9 pick a piece",
+    "u undo   U redo",
+    "q quit",
+];
+
+// What undo and redo go back and forth between.
+#[derive(Clone)]
+struct Snapshot {
+    shapes: Vec<Piece>,
+    at: Vec<Option<(usize, usize)>>,
+}
+
+struct Game {
+    layout: Vec<Vec<Cell>>,
+    // Each piece in its current orientation.
+    shapes: Vec<Piece>,
+    // Top left corner of each piece on the board.
+    at: Vec<Option<(usize, usize)>>,
+    selected: usize,
+    // Top left corner of the floating piece.
+    cursor: (usize, usize),
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    start: Instant,
+    // The time it took, once solved.
+    solved: Option<Duration>,
+    message: String,
+}
+
+impl Game {
+    fn new(board: &Board) -> Game {
+        return Game {
+            layout: board.layout().to_vec(),
+            shapes: board.pieces.iter().map(|p| p[0].clone()).collect(),
+            at: vec![None; board.pieces.len()],
+            selected: 0,
+            cursor: (0, 0),
+            undo: vec![],
+            redo: vec![],
+            start: Instant::now(),
+            solved: None,
+            message: String::new(),
+        };
+    }
+
+    // The board with the dropped pieces on it.
+    fn grid(&self) -> Vec<Vec<Cell>> {
+        let mut res = self.layout.clone();
+        for (i, at) in self.at.iter().enumerate() {
+            let Some((r, c)) = *at else { continue };
+            for (pr, pc) in self.shapes[i].filled() {
+                res[r + pr][c + pc] = Cell::Piece(i);
+            }
+        }
+        return res;
+    }
+
+    fn floating(&self) -> Option<&Piece> {
+        return if self.at[self.selected].is_none() { Some(&self.shapes[self.selected]) } else { None };
+    }
+
+    fn fits(&self) -> bool {
+        let (r, c) = self.cursor;
+        return !self.shapes[self.selected].fit(&self.grid(), r, c).is_empty();
+    }
+
+    // Keeps the floating piece on the board.
+    fn clamp(&mut self) {
+        let piece = &self.shapes[self.selected];
+        let (h, w) = (self.layout.len(), self.layout[0].len());
+        self.cursor.0 = self.cursor.0.min(h.saturating_sub(piece.height()));
+        self.cursor.1 = self.cursor.1.min(w.saturating_sub(piece.width()));
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        return Snapshot { shapes: self.shapes.clone(), at: self.at.clone() };
+    }
+
+    fn restore(&mut self, s: Snapshot) {
+        self.shapes = s.shapes;
+        self.at = s.at;
+        self.clamp();
+    }
+
+    fn record(&mut self) {
+        self.undo.push(self.snapshot());
+        self.redo.clear();
+    }
+
+    fn select(&mut self, i: usize) {
+        if i < self.shapes.len() {
+            self.selected = i;
+            self.clamp();
+        }
+    }
+
+    fn step(&mut self, dr: isize, dc: isize) {
+        if self.floating().is_none() {
+            return;
+        }
+        let r = self.cursor.0.saturating_add_signed(dr);
+        let c = self.cursor.1.saturating_add_signed(dc);
+        self.cursor = (r, c);
+        self.clamp();
+    }
+
+    fn turn(&mut self, flip: bool) {
+        let Some(piece) = self.floating() else { return };
+        let allowed = match piece.motion {
+            Motion::Free => true,
+            Motion::NoFlip => !flip,
+            Motion::Fixed => false,
+        };
+        if !allowed {
+            self.message = format!("piece {} can't be {}", piece.name, if flip { "flipped" } else { "rotated" });
+            return;
+        }
+        let turned = if flip { piece.rev() } else { piece.rotate() };
+        if turned.height() > self.layout.len() || turned.width() > self.layout[0].len() {
+            self.message = format!("piece {} doesn't fit on the board that way", piece.name);
+            return;
+        }
+        self.shapes[self.selected] = turned;
+        self.clamp();
+    }
+
+    // Drops the floating piece, or picks the selected one back up.
+    fn toggle(&mut self) {
+        if let Some((r, c)) = self.at[self.selected] {
+            self.record();
+            self.at[self.selected] = None;
+            self.cursor = (r, c);
+            return;
+        }
+        if !self.fits() {
+            self.message = "the piece doesn't fit there".to_string();
+            return;
+        }
+        self.record();
+        self.at[self.selected] = Some(self.cursor);
+        if self.at.iter().all(Option::is_some) {
+            self.solved = Some(self.start.elapsed());
+            return;
+        }
+        let n = self.shapes.len();
+        let next = (1..n).map(|k| (self.selected + k) % n).find(|&i| self.at[i].is_none());
+        self.select(next.unwrap_or(self.selected));
+    }
+
+    fn back(&mut self, redo: bool) {
+        let (from, to) = if redo { (&mut self.redo, &mut self.undo) } else { (&mut self.undo, &mut self.redo) };
+        let Some(s) = from.pop() else { return };
+        to.push(Snapshot { shapes: self.shapes.clone(), at: self.at.clone() });
+        self.restore(s);
+        let done = self.at.iter().all(Option::is_some);
+        self.solved = if done { self.solved.or(Some(self.start.elapsed())) } else { None };
+    }
+
+    // False once the player quits.
+    fn key(&mut self, key: KeyEvent) -> bool {
+        self.message.clear();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if ctrl => return false,
+            KeyCode::Char('u') => self.back(false),
+            KeyCode::Char('z') if ctrl => self.back(false),
+            KeyCode::Char('U') => self.back(true),
+            KeyCode::Char('y') if ctrl => self.back(true),
+            _ if self.solved.is_some() => {}
+            KeyCode::Up => self.step(-1, 0),
+            KeyCode::Down => self.step(1, 0),
+            KeyCode::Left => self.step(0, -1),
+            KeyCode::Right => self.step(0, 1),
+            KeyCode::Char('r') => self.turn(false),
+            KeyCode::Char('f') => self.turn(true),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle(),
+            KeyCode::Tab => self.select((self.selected + 1) % self.shapes.len()),
+            KeyCode::BackTab => self.select((self.selected + self.shapes.len() - 1) % self.shapes.len()),
+            KeyCode::Char(d @ '1'..='9') => self.select(d as usize - '1' as usize),
+            _ => {}
+        }
+        return true;
+    }
+}
+
+fn rgb(hex: &str) -> Color {
+    let part = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    return Color::Rgb { r: part(1), g: part(3), b: part(5) };
+}
+
+fn clock(d: Duration) -> String {
+    return format!("{:02}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+}
+
+fn draw(out: &mut io::Stdout, puzzle: &Puzzle, game: &Game) -> io::Result<()> {
+    let mut row = 0;
+    let mut line = |out: &mut io::Stdout| -> io::Result<()> {
+        queue!(out, ResetColor, MoveTo(0, row))?;
+        row += 1;
+        return Ok(());
+    };
+    queue!(out, Clear(ClearType::All))?;
+    line(out)?;
+    let time = clock(game.solved.unwrap_or_else(|| game.start.elapsed()));
+    queue!(out, Print(format!("{}   {}", puzzle.name, time)))?;
+    line(out)?;
+
+    let grid = game.grid();
+    let mut over = vec![vec![false; grid[0].len()]; grid.len()];
+    if let Some(piece) = game.floating() {
+        // A piece bigger than the board only shows the part on it.
+        for (r, c) in piece.filled() {
+            if let Some(cell) = over.get_mut(game.cursor.0 + r).and_then(|row| row.get_mut(game.cursor.1 + c)) {
+                *cell = true;
+            }
+        }
+    }
+    let fits = game.fits();
+    for (r, cells) in grid.iter().enumerate() {
+        line(out)?;
+        for (c, cell) in cells.iter().enumerate() {
+            let (color, shown) = match cell {
+                _ if over[r][c] => {
+                    let color = if fits { svg::color(game.shapes[game.selected].glyph, game.selected) } else { "#ff0000" };
+                    (Some(rgb(color)), "▒▒".to_string())
+                }
+                Cell::Piece(i) if *i == game.selected => (Some(rgb(svg::color(game.shapes[*i].glyph, *i))), "[]".to_string()),
+                Cell::Piece(i) => (Some(rgb(svg::color(game.shapes[*i].glyph, *i))), "  ".to_string()),
+                Cell::Target(label) => (None, text::short(label)),
+                Cell::Blocked => (Some(Color::AnsiValue(236)), "  ".to_string()),
+                Cell::Empty => (None, "· ".to_string()),
+            };
+            match color {
+                Some(color) => queue!(out, SetBackgroundColor(color), Print(shown), ResetColor)?,
+                None => queue!(out, Print(shown))?,
+            }
+        }
+    }
+    line(out)?;
+
+    for (i, piece) in game.shapes.iter().enumerate() {
+        line(out)?;
+        let mark = if i == game.selected { '>' } else { ' ' };
+        let state = if game.at[i].is_some() { "placed" } else { "" };
+        queue!(out, Print(format!("{} {:>2} ", mark, i + 1)),
+               SetBackgroundColor(rgb(svg::color(piece.glyph, i))), Print("  "), ResetColor,
+               Print(format!(" {:<8} {}", piece.name, state)))?;
+    }
+    line(out)?;
+    line(out)?;
+    match game.solved {
+        Some(d) => queue!(out, Print(format!("Solved! in {}", clock(d))))?,
+        None => queue!(out, Print(&game.message))?,
+    }
+    line(out)?;
+    for help in HELP {
+        line(out)?;
+        queue!(out, Print(help))?;
+    }
+    return out.flush();
+}
+
+// Puts the terminal back however `run` ends.
+struct Screen;
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn play(puzzle: &Puzzle, board: &Board) -> io::Result<()> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    let _screen = Screen;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    let mut game = Game::new(board);
+    loop {
+        draw(&mut out, puzzle, &game)?;
+        // Wakes up now and then to move the clock on.
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !game.key(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// The board for the chosen targets, full screen until the player quits.
+pub fn run(puzzle: &Puzzle, board: &Board) -> Result<(), Error> {
+    return play(puzzle, board).map_err(|e| Error::Io(format!("terminal: {}", e)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(puzzle: &Puzzle, targets: &[&str]) -> Game {
+        return Game::new(&Board::new(puzzle, targets).unwrap());
+    }
+
+    fn press(game: &mut Game, keys: &[KeyCode]) {
+        for &code in keys {
+            assert!(game.key(KeyEvent::new(code, KeyModifiers::NONE)));
+        }
+    }
+
+    // Two rows of three, for pieces that fill them.
+    fn rows(pieces: &str) -> Puzzle {
+        return Puzzle::parse("test", &format!("[board]\n...\n...\n[pieces]\n{}", pieces)).unwrap();
+    }
+
+    #[test]
+    fn moves() {
+        let mut game = game(&Puzzle::month_day(), &["JAN", "1"]);
+        press(&mut game, &[KeyCode::Right; 10]);
+        press(&mut game, &[KeyCode::Down; 10]);
+        assert_eq!(game.cursor, (4, 4));
+        press(&mut game, &[KeyCode::Up, KeyCode::Left]);
+        assert_eq!(game.cursor, (3, 3));
+        // The next piece is four wide and two high.
+        press(&mut game, &[KeyCode::Tab, KeyCode::Down, KeyCode::Down]);
+        assert_eq!((game.selected, game.cursor), (1, (5, 3)));
+        press(&mut game, &[KeyCode::Char('r')]);
+        assert_eq!((game.shapes[1].height(), game.shapes[1].width()), (4, 2));
+        assert_eq!(game.cursor, (3, 3));
+        press(&mut game, &[KeyCode::BackTab, KeyCode::BackTab, KeyCode::Char('3')]);
+        assert_eq!(game.selected, 2);
+        assert!(!game.key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn drops() {
+        let mut game = game(&rows("aaa\n\nbbb\n"), &[]);
+        press(&mut game, &[KeyCode::Char(' ')]);
+        assert_eq!((game.at[0], game.selected), (Some((0, 0)), 1));
+        press(&mut game, &[KeyCode::Char(' ')]);
+        assert_eq!((game.at[1], game.message.as_str()), (None, "the piece doesn't fit there"));
+        press(&mut game, &[KeyCode::Down, KeyCode::Char(' ')]);
+        assert!(game.solved.is_some());
+        assert_eq!(game.grid(), vec![vec![Cell::Piece(0); 3], vec![Cell::Piece(1); 3]]);
+        // Only undo and redo once it's solved.
+        press(&mut game, &[KeyCode::Up, KeyCode::Char('1')]);
+        assert_eq!((game.selected, game.cursor), (1, (1, 0)));
+        press(&mut game, &[KeyCode::Char('u')]);
+        assert_eq!((game.at[1], game.solved), (None, None));
+        press(&mut game, &[KeyCode::Char('U')]);
+        assert!(game.solved.is_some());
+        press(&mut game, &[KeyCode::Char('u'), KeyCode::Char('u')]);
+        assert_eq!(game.at, vec![None, None]);
+        press(&mut game, &[KeyCode::Char('U'), KeyCode::Char('1'), KeyCode::Char(' ')]);
+        assert_eq!((game.at[0], game.cursor), (None, (0, 0)));
+        // Lifting a piece clears what could be redone.
+        assert!(game.redo.is_empty());
+    }
+
+    #[test]
+    fn motions() {
+        let mut game = game(&rows("piece a fixed\naa\n\npiece b no-flip\nb.\nbb\n"), &[]);
+        press(&mut game, &[KeyCode::Char('r')]);
+        assert_eq!(game.message, "piece a can't be rotated");
+        press(&mut game, &[KeyCode::Char('f')]);
+        assert_eq!(game.message, "piece a can't be flipped");
+        press(&mut game, &[KeyCode::Tab, KeyCode::Char('f')]);
+        assert_eq!(game.message, "piece b can't be flipped");
+        press(&mut game, &[KeyCode::Char('r')]);
+        assert_eq!((game.message.as_str(), game.shapes[1].cells.clone()),
+                   ("", vec![vec![false, true], vec![true, true]]));
+    }
+
+    #[test]
+    fn turns_stay_on_the_board() {
+        let mut game = game(&rows("aaa\n\nbbb\n"), &[]);
+        press(&mut game, &[KeyCode::Char('r')]);
+        assert_eq!(game.message, "piece a doesn't fit on the board that way");
+        assert_eq!(game.shapes[0].cells, vec![vec![true; 3]]);
+        press(&mut game, &[KeyCode::Char('f')]);
+        assert_eq!(game.message, "");
+    }
+}
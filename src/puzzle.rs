@@ -0,0 +1,457 @@
+use std::fs;
+use std::path::Path;
+
+use crate::board::Mask;
+use crate::date;
+use crate::piece::{Cell, Motion, Piece};
+use crate::Error;
+
+pub const PIECES : [&[&str]; 8]  = [
+    &[ "🟥..", "🟥..", "🟥🟥🟥" ],
+    &[ "🟦🟦🟦🟦", ".🟦.." ],
+    &[ "🟧🟧..", ".🟧🟧🟧" ],
+    &[ "🟨🟨🟨", "🟨🟨🟨" ],
+    &[ "🟩..", "🟩🟩🟩", "..🟩" ],
+    &[ "🟪...", "🟪🟪🟪🟪" ],
+    &[ "🟫.🟫", "🟫🟫🟫" ],
+    &[ "⬜⬜.", "⬜⬜⬜" ]
+];
+
+pub const BOARD : [&str; 7] = [
+    "......⬛",
+    "......⬛",
+    ".......",
+    ".......",
+    ".......",
+    ".......",
+    "...⬛⬛⬛⬛",
+];
+
+// The label of each cell of `BOARD`, "" for blocked cells.
+pub const BOARD_LABELS : [&[&str]; 7] = [
+    &[ "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "" ],
+    &[ "JUL", "AUG", "SEP", "OCT", "NOV", "DEC", "" ],
+    &[ "1", "2", "3", "4", "5", "6", "7" ],
+    &[ "8", "9", "10", "11", "12", "13", "14" ],
+    &[ "15", "16", "17", "18", "19", "20", "21" ],
+    &[ "22", "23", "24", "25", "26", "27", "28" ],
+    &[ "29", "30", "31", "", "", "", "" ],
+];
+
+// The edition with weekdays.
+pub const WEEKDAY_PIECES : [&[&str]; 10]  = [
+    &[ "🟥..", "🟥..", "🟥🟥🟥" ],
+    &[ "🟦🟦🟦🟦", ".🟦.." ],
+    &[ "🟧🟧..", ".🟧🟧🟧" ],
+    &[ "🟨.🟨", "🟨🟨🟨" ],
+    &[ "🟩..", "🟩🟩🟩", "..🟩" ],
+    &[ "🟪...", "🟪🟪🟪🟪" ],
+    &[ "🟫🟫.", "🟫🟫🟫" ],
+    &[ "⬜⬜⬜⬜" ],
+    &[ "🔴..", "🔴🔴🔴" ],
+    &[ "🔵🔵.", ".🔵🔵" ],
+];
+
+pub const WEEKDAY_BOARD : [&str; 8] = [
+    "......⬛",
+    "......⬛",
+    ".......",
+    ".......",
+    ".......",
+    ".......",
+    ".......",
+    "⬛⬛⬛⬛...",
+];
+
+pub const WEEKDAY_BOARD_LABELS : [&[&str]; 8] = [
+    &[ "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "" ],
+    &[ "JUL", "AUG", "SEP", "OCT", "NOV", "DEC", "" ],
+    &[ "1", "2", "3", "4", "5", "6", "7" ],
+    &[ "8", "9", "10", "11", "12", "13", "14" ],
+    &[ "15", "16", "17", "18", "19", "20", "21" ],
+    &[ "22", "23", "24", "25", "26", "27", "28" ],
+    &[ "29", "30", "31", "SUN", "MON", "TUE", "WED" ],
+    &[ "", "", "", "", "THU", "FRI", "SAT" ],
+];
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub name: String,
+    // Only `Empty` and `Blocked` cells.
+    pub board: Vec<Vec<Cell>>,
+    pub pieces: Vec<Piece>,
+    // Cells that can be picked as targets, as (label, row, col).
+    pub labels: Vec<(String, usize, usize)>,
+}
+
+// '.' for open cells, anything else for blocked ones.
+fn layout<S: AsRef<str>>(rows: &[S]) -> Vec<Vec<Cell>> {
+    return rows.iter()
+        .map(|r| r.as_ref().chars().map(|c| if c == '.' { Cell::Empty } else { Cell::Blocked }).collect())
+        .collect();
+}
+
+fn pieces(shapes: &[&[&str]]) -> Vec<Piece> {
+    return shapes.iter().enumerate()
+        .map(|(i, p)| Piece::from(i, p).expect("built-in pieces are well formed"))
+        .collect();
+}
+
+// The lines of one piece in a puzzle file.
+struct PieceLines<'a> {
+    // The `piece NAME [GLYPH]` line, as words.
+    head: Option<(usize, Vec<(usize, &'a str)>)>,
+    rows: Vec<(usize, &'a str)>,
+}
+
+fn labels(grid: &[&[&str]]) -> Vec<(String, usize, usize)> {
+    let mut res = vec![];
+    for (r, row) in grid.iter().enumerate() {
+        for (c, label) in row.iter().enumerate() {
+            if !label.is_empty() {
+                res.push((label.to_string(), r, c));
+            }
+        }
+    }
+    return res;
+}
+
+// Byte offset to 1-based character column.
+fn column(line: &str, offset: usize) -> usize {
+    return line[..offset].chars().count() + 1;
+}
+
+// Whitespace separated words with their columns.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut res = vec![];
+    let mut start = None;
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                res.push((column(line, s), &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    return res;
+}
+
+impl Puzzle {
+    pub fn month_day() -> Puzzle {
+        return Puzzle {
+            name: "month-day".to_string(),
+            board: layout(&BOARD),
+            pieces: pieces(&PIECES),
+            labels: labels(&BOARD_LABELS),
+        };
+    }
+
+    pub fn weekday() -> Puzzle {
+        return Puzzle {
+            name: "weekday".to_string(),
+            board: layout(&WEEKDAY_BOARD),
+            pieces: pieces(&WEEKDAY_PIECES),
+            labels: labels(&WEEKDAY_BOARD_LABELS),
+        };
+    }
+
+    pub fn label(&self, name: &str) -> Option<&(String, usize, usize)> {
+        return self.labels.iter().find(|l| l.0.eq_ignore_ascii_case(name));
+    }
+
+    // Makes every piece turn at most as freely as `motion` allows.
+    pub fn restrict(&mut self, motion: Motion) {
+        for p in &mut self.pieces {
+            p.motion = p.motion.max(motion);
+        }
+    }
+
+    pub fn has_weekdays(&self) -> bool {
+        return (0..7).any(|w| date::weekday_label(w).is_ok_and(|l| self.label(&l).is_some()));
+    }
+
+    pub fn load(path: &Path) -> Result<Puzzle, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::Io(format!("{}: {}", path.display(), e)))?;
+        let mut res = Puzzle::parse(&path.display().to_string(), &text)?;
+        if let Some(stem) = path.file_stem() {
+            res.name = stem.to_string_lossy().to_string();
+        }
+        return Ok(res);
+    }
+
+    // Lines starting with "//" are comments. Sections start with a
+    // `[board]`, `[pieces]` or `[labels]` line. The board and the pieces
+    // are grids as in `BOARD` and `PIECES`, with blank lines between the
+    // pieces. A piece may start with a `piece NAME [GLYPH] [no-flip|fixed]`
+    // line; its rows then still use '.' for empty cells, but any glyph can
+    // be shown for it, '.' included. `no-flip` keeps the piece the side up
+    // it's drawn, `fixed` also stops it from turning. Each label line is
+    // `NAME ROW COL` naming an open cell, rows and columns counted from 0.
+    // Dates are covered through the labels JAN to DEC, 1 to 31 and SUN to
+    // SAT.
+    pub fn parse(source: &str, text: &str) -> Result<Puzzle, Error> {
+        let err = |line: usize, column: usize, message: &str| Error::Parse {
+            source: source.to_string(), line, column, message: message.to_string(),
+        };
+        let mut section = "";
+        let mut board: Vec<(usize, &str)> = vec![];
+        let mut pieces: Vec<PieceLines> = vec![];
+        let mut labels: Vec<(usize, Vec<(usize, &str)>)> = vec![];
+        let mut blank = true;
+        let mut last = 0;
+        for (i, line) in text.lines().enumerate() {
+            let n = i + 1;
+            let line = line.trim_end();
+            last = n;
+            if line.starts_with("//") {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if !["board", "pieces", "labels"].contains(&name) {
+                    return Err(err(n, 2, &format!("unknown section [{}]", name)));
+                }
+                section = name;
+                blank = true;
+                continue;
+            }
+            if line.is_empty() {
+                blank = true;
+                continue;
+            }
+            match section {
+                "board" => {
+                    if blank && !board.is_empty() {
+                        return Err(err(n, 1, "blank line inside the board"));
+                    }
+                    board.push((n, line));
+                }
+                "pieces" => {
+                    let head = words(line);
+                    if head[0].1 == "piece" {
+                        pieces.push(PieceLines { head: Some((n, head)), rows: vec![] });
+                    } else {
+                        if blank && !pieces.last().is_some_and(|p| p.rows.is_empty()) {
+                            pieces.push(PieceLines { head: None, rows: vec![] });
+                        }
+                        pieces.last_mut().unwrap().rows.push((n, line));
+                    }
+                }
+                "labels" => labels.push((n, words(line))),
+                _ => return Err(err(n, 1, "expected [board], [pieces] or [labels]")),
+            }
+            blank = false;
+        }
+
+        if board.is_empty() {
+            return Err(err(last + 1, 1, "no [board] section"));
+        }
+        let width = board[0].1.chars().count();
+        for &(n, row) in &board {
+            let len = row.chars().count();
+            if len != width {
+                return Err(err(n, len.min(width) + 1, &format!("board rows must all be {} cells wide", width)));
+            }
+        }
+        if width * board.len() > Mask::BITS as usize {
+            return Err(err(board[0].0, 1, &format!("board has {} cells, at most {} are supported",
+                width * board.len(), Mask::BITS)));
+        }
+        if pieces.is_empty() {
+            return Err(err(last + 1, 1, "no [pieces] section"));
+        }
+        if pieces.len() > u64::BITS as usize {
+            return Err(err(last + 1, 1, &format!("at most {} pieces are supported", u64::BITS)));
+        }
+        let mut shapes = vec![];
+        for (i, piece) in pieces.iter().enumerate() {
+            let mut name = None;
+            let mut glyph = None;
+            let mut motion = Motion::Free;
+            if let Some((n, head)) = &piece.head {
+                let Some(&(_, word)) = head.get(1) else {
+                    return Err(err(*n, 1, "expected piece NAME [GLYPH] [no-flip|fixed]"));
+                };
+                name = Some(word);
+                for &(c, word) in &head[2..] {
+                    let mut chars = word.chars();
+                    match (word, chars.next(), chars.next()) {
+                        ("no-flip", ..) if motion == Motion::Free => motion = Motion::NoFlip,
+                        ("fixed", ..) if motion == Motion::Free => motion = Motion::Fixed,
+                        ("no-flip" | "fixed", ..) => {
+                            return Err(err(*n, c, "only one of no-flip and fixed may be given"));
+                        }
+                        (_, Some(g), None) if glyph.is_none() => glyph = Some(g),
+                        (_, _, None) => return Err(err(*n, c, "glyph given twice")),
+                        _ => return Err(err(*n, c, &format!("unknown piece option {}", word))),
+                    }
+                }
+            }
+            let Some(&(first, row)) = piece.rows.first() else {
+                return Err(err(piece.head.as_ref().unwrap().0, 1, "piece has no rows"));
+            };
+            let width = row.chars().count();
+            for &(n, row) in &piece.rows {
+                let len = row.chars().count();
+                if len != width {
+                    return Err(err(n, len.min(width) + 1, &format!("piece rows must all be {} cells wide", width)));
+                }
+            }
+            if piece.rows.iter().all(|(_, row)| row.chars().all(|c| c == '.')) {
+                return Err(err(first, 1, "piece has no cells"));
+            }
+            let rows: Vec<&str> = piece.rows.iter().map(|&(_, row)| row).collect();
+            let mut shape = Piece::from(i, &rows)?;
+            if let Some(name) = name {
+                shape.name = name.to_string();
+            }
+            if let Some(glyph) = glyph {
+                shape.glyph = glyph;
+            }
+            shape.motion = motion;
+            shapes.push(shape);
+        }
+
+        let cells: Vec<Vec<char>> = board.iter().map(|(_, row)| row.chars().collect()).collect();
+        let mut res: Vec<(String, usize, usize)> = vec![];
+        for (n, words) in labels {
+            let [(_, name), (rc, row), (cc, col)] = words[..] else {
+                let c = words.get(3).map_or(1, |w| w.0);
+                return Err(err(n, c, "expected NAME ROW COL"));
+            };
+            let r: usize = row.parse().map_err(|_| err(n, rc, "row must be a number"))?;
+            let c: usize = col.parse().map_err(|_| err(n, cc, "column must be a number"))?;
+            if r >= cells.len() {
+                return Err(err(n, rc, &format!("row must be less than {}", cells.len())));
+            }
+            if c >= width {
+                return Err(err(n, cc, &format!("column must be less than {}", width)));
+            }
+            if cells[r][c] != '.' {
+                return Err(err(n, cc, "label on a blocked cell"));
+            }
+            if res.iter().any(|l| l.0.eq_ignore_ascii_case(name)) {
+                return Err(err(n, 1, &format!("label {} given twice", name)));
+            }
+            res.push((name.to_string(), r, c));
+        }
+        return Ok(Puzzle {
+            name: source.to_string(),
+            board: layout(&board.iter().map(|&(_, row)| row).collect::<Vec<_>>()),
+            pieces: shapes,
+            labels: res,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIECE: &str = "[pieces]\nxx\n";
+
+    // Where parsing `text` fails, as (line, column).
+    fn error_at(text: &str) -> (usize, usize) {
+        match Puzzle::parse("test", text) {
+            Err(Error::Parse { line, column, .. }) => return (line, column),
+            res => panic!("expected a parse error, got {:?}", res.map(|p| p.name)),
+        }
+    }
+
+    fn with_board(rest: &str) -> String {
+        return format!("[board]\n...\n...\n{}", rest);
+    }
+
+    #[test]
+    fn parses() {
+        let puzzle = Puzzle::parse("weekday", include_str!("../puzzles/weekday.txt")).unwrap();
+        assert!(puzzle.has_weekdays());
+        assert_eq!(puzzle.labels.len(), 12 + 31 + 7);
+        let puzzle = Puzzle::parse("test", &with_board(&format!("{}[labels]\nA 0 1\n", PIECE))).unwrap();
+        assert_eq!(puzzle.board, vec![vec![Cell::Empty; 3]; 2]);
+        assert_eq!(puzzle.pieces, vec![Piece::from(0, &["xx"]).unwrap()]);
+        assert_eq!(puzzle.labels, vec![("A".to_string(), 0, 1)]);
+        assert!(!puzzle.has_weekdays());
+    }
+
+    #[test]
+    fn built_in_labels() {
+        let puzzle = Puzzle::month_day();
+        assert_eq!(puzzle.labels.len(), 12 + 31);
+        assert_eq!(puzzle.label("dec"), Some(&("DEC".to_string(), 1, 5)));
+        assert_eq!(puzzle.label("31"), Some(&("31".to_string(), 6, 2)));
+        assert_eq!(puzzle.label("MON"), None);
+        let puzzle = Puzzle::weekday();
+        assert_eq!(puzzle.labels.len(), 12 + 31 + 7);
+        assert_eq!(puzzle.label("sat"), Some(&("SAT".to_string(), 7, 6)));
+    }
+
+    #[test]
+    fn section_errors() {
+        assert_eq!(error_at("[boards]\n"), (1, 2));
+        assert_eq!(error_at("// comment\n...\n"), (2, 1));
+        assert_eq!(error_at("[board]\n...\n\n...\n"), (4, 1));
+        assert_eq!(error_at(PIECE), (3, 1));
+        assert_eq!(error_at("[board]\n...\n"), (3, 1));
+    }
+
+    #[test]
+    fn board_errors() {
+        assert_eq!(error_at(&format!("[board]\n...\n..\n{}", PIECE)), (3, 3));
+        assert_eq!(error_at(&format!("[board]\n...\n....\n{}", PIECE)), (3, 4));
+        let big = vec![".".repeat(13); 5].join("\n");
+        assert_eq!(error_at(&format!("[board]\n{}\n{}", big, PIECE)), (2, 1));
+        let pieces = vec!["x"; 65].join("\n\n");
+        assert_eq!(error_at(&with_board(&format!("[pieces]\n{}\n", pieces))), (134, 1));
+    }
+
+    #[test]
+    fn piece_names() {
+        let text = with_board("[pieces]\npiece long\nxx\n\nx\npiece dot .\nx.\nxx\n");
+        let puzzle = Puzzle::parse("test", &text).unwrap();
+        let names: Vec<_> = puzzle.pieces.iter().map(|p| (p.index, p.name.as_str(), p.glyph)).collect();
+        assert_eq!(names, vec![(0, "long", 'x'), (1, "x", 'x'), (2, "dot", '.')]);
+        assert_eq!(puzzle.pieces[2].size(), 3);
+    }
+
+    #[test]
+    fn piece_motions() {
+        let text = with_board("[pieces]\npiece a no-flip\nxx\n\npiece b fixed *\nxx\n\nxx\n");
+        let mut puzzle = Puzzle::parse("test", &text).unwrap();
+        let motions = |puzzle: &Puzzle| puzzle.pieces.iter().map(|p| p.motion).collect::<Vec<_>>();
+        assert_eq!(motions(&puzzle), vec![Motion::NoFlip, Motion::Fixed, Motion::Free]);
+        assert_eq!(puzzle.pieces[1].glyph, '*');
+        puzzle.restrict(Motion::NoFlip);
+        assert_eq!(motions(&puzzle), vec![Motion::NoFlip, Motion::Fixed, Motion::NoFlip]);
+        puzzle.restrict(Motion::Fixed);
+        assert_eq!(motions(&puzzle), vec![Motion::Fixed; 3]);
+    }
+
+    #[test]
+    fn piece_errors() {
+        assert_eq!(error_at(&with_board("[pieces]\npiece a no-flip fixed\nxx\n")), (5, 17));
+        assert_eq!(error_at(&with_board("[pieces]\npiece a x fixed y\nxx\n")), (5, 17));
+        assert_eq!(error_at(&with_board("[pieces]\npiece\nxx\n")), (5, 1));
+        assert_eq!(error_at(&with_board("[pieces]\npiece a bc\nxx\n")), (5, 9));
+        assert_eq!(error_at(&with_board("[pieces]\npiece a b c\nxx\n")), (5, 11));
+        assert_eq!(error_at(&with_board("[pieces]\npiece a\n\npiece b\nxx\n")), (5, 1));
+        assert_eq!(error_at(&with_board("[pieces]\nxx\nx\n")), (6, 2));
+        assert_eq!(error_at(&with_board("[pieces]\n..\n..\n")), (5, 1));
+    }
+
+    #[test]
+    fn label_errors() {
+        let labels = |l: &str| with_board(&format!("{}[labels]\n{}\n", PIECE, l));
+        assert_eq!(error_at(&labels("A 0")), (7, 1));
+        assert_eq!(error_at(&labels("A 0 1 2")), (7, 7));
+        assert_eq!(error_at(&labels("A x 1")), (7, 3));
+        assert_eq!(error_at(&labels("A 0 y")), (7, 5));
+        assert_eq!(error_at(&labels("A 2 0")), (7, 3));
+        assert_eq!(error_at(&labels("A 0 3")), (7, 5));
+        assert_eq!(error_at(&labels("A 0 0\na 1 1")), (8, 1));
+        let blocked = format!("[board]\n.#.\n{}[labels]\nA 0 1\n", PIECE);
+        assert_eq!(error_at(&blocked), (6, 5));
+    }
+}
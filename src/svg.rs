@@ -0,0 +1,196 @@
+// Vector drawings of solutions. Each piece is one outlined polygon, so
+// the borders between pieces are drawn once and there are no seams
+// between the cells of a piece.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::board::{Board, Solution};
+use crate::piece::Cell;
+
+const CELL: usize = 40;
+const MARGIN: usize = 10;
+// Between the solutions of a sheet.
+const GAP: usize = 20;
+
+const PALETTE: [&str; 8] = [
+    "#e6194b", "#4363d8", "#f58231", "#ffe119", "#3cb44b", "#911eb4", "#9a6324", "#46f0f0",
+];
+
+pub(crate) fn color(glyph: char, index: usize) -> &'static str {
+    return match glyph {
+        '🟥' => "#e74c3c",
+        '🟧' => "#f39c12",
+        '🟨' => "#f1c40f",
+        '🟩' => "#2ecc71",
+        '🟦' => "#3498db",
+        '🟪' => "#9b59b6",
+        '🟫' => "#8d6e63",
+        '⬜' => "#ecf0f1",
+        '🔴' => "#922b21",
+        '🔵' => "#1a5276",
+        _ => PALETTE[index % PALETTE.len()],
+    };
+}
+
+// The outline of a set of cells as closed paths, walking clockwise along
+// the cell sides that face a cell outside the set.
+fn outline(cells: &[(usize, usize)]) -> String {
+    let inside = |r: isize, c: isize| r >= 0 && c >= 0 && cells.contains(&(r as usize, c as usize));
+    let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for &(r, c) in cells {
+        let (ri, ci) = (r as isize, c as isize);
+        // Corners as (x, y).
+        let sides = [
+            (!inside(ri - 1, ci), (c, r), (c + 1, r)),
+            (!inside(ri, ci + 1), (c + 1, r), (c + 1, r + 1)),
+            (!inside(ri + 1, ci), (c + 1, r + 1), (c, r + 1)),
+            (!inside(ri, ci - 1), (c, r + 1), (c, r)),
+        ];
+        for (open, from, to) in sides {
+            if open {
+                edges.entry(from).or_default().push(to);
+            }
+        }
+    }
+
+    let mut res = String::new();
+    let mut starts: Vec<_> = edges.keys().copied().collect();
+    starts.sort();
+    for start in starts {
+        while edges.get(&start).is_some_and(|e| !e.is_empty()) {
+            let mut points = vec![start];
+            let mut at = start;
+            loop {
+                at = edges.get_mut(&at).unwrap().pop().unwrap();
+                if at == start {
+                    break;
+                }
+                points.push(at);
+            }
+            // Only the corners where the direction changes.
+            let n = points.len();
+            let corners: Vec<_> = (0..n).filter(|&i| {
+                let (a, b, c) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+                return !(a.0 == b.0 && b.0 == c.0 || a.1 == b.1 && b.1 == c.1);
+            }).map(|i| points[i]).collect();
+            for (i, (x, y)) in corners.iter().enumerate() {
+                let _ = write!(res, "{}{} {} ", if i == 0 { "M" } else { "L" }, x * CELL, y * CELL);
+            }
+            res.push('Z');
+        }
+    }
+    return res;
+}
+
+// The solution drawn with its top left corner at (x, y).
+fn group(board: &Board, s: &Solution, x: usize, y: usize) -> String {
+    let mut res = format!("<g transform=\"translate({} {})\">\n", x + MARGIN, y + MARGIN);
+    let mut open = vec![];
+    for (r, row) in s.grid.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            if *cell != Cell::Blocked {
+                open.push((r, c));
+                continue;
+            }
+            let _ = writeln!(res, "<rect x=\"{}\" y=\"{}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"#2c3e50\"/>",
+                             c * CELL, r * CELL);
+        }
+    }
+    let border = outline(&open);
+    let _ = writeln!(res, "<path d=\"{}\" fill=\"#fdfdfd\"/>", border);
+    for p in &s.placements {
+        let cells: Vec<_> = open.iter().copied().filter(|&(r, c)| s.grid[r][c] == Cell::Piece(p.piece)).collect();
+        let glyph = board.pieces[p.piece][0].glyph;
+        let _ = writeln!(res, "<path d=\"{}\" fill=\"{}\" stroke=\"#222\" stroke-width=\"2\" \
+                               stroke-linejoin=\"round\"/>", outline(&cells), color(glyph, p.piece));
+    }
+    // On top, so the pieces along the edge don't cover half of it.
+    let _ = writeln!(res, "<path d=\"{}\" fill=\"none\" stroke=\"#2c3e50\" stroke-width=\"3\"/>", border);
+    for &(r, c) in &open {
+        let Cell::Target(label) = &s.grid[r][c] else { continue };
+        let _ = writeln!(res, "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"14\" \
+                               text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                         c * CELL + CELL / 2, r * CELL + CELL / 2, escape(label));
+    }
+    res.push_str("</g>\n");
+    return res;
+}
+
+fn escape(s: &str) -> String {
+    return s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+}
+
+fn document(width: usize, height: usize, body: &str) -> String {
+    return format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+                    viewBox=\"0 0 {width} {height}\">\n{body}</svg>\n");
+}
+
+fn size(s: &Solution) -> (usize, usize) {
+    return (s.grid[0].len() * CELL + 2 * MARGIN, s.grid.len() * CELL + 2 * MARGIN);
+}
+
+// One solution of `board` as a standalone SVG document.
+pub fn render(board: &Board, s: &Solution) -> String {
+    let (w, h) = size(s);
+    return document(w, h, &group(board, s, 0, 0));
+}
+
+// Several solutions side by side, `columns` to a row.
+pub fn sheet(board: &Board, solutions: &[Solution], columns: usize) -> String {
+    let Some(first) = solutions.first() else {
+        return document(0, 0, "");
+    };
+    let (w, h) = size(first);
+    let columns = columns.clamp(1, solutions.len());
+    let rows = solutions.len().div_ceil(columns);
+    let mut body = String::new();
+    for (i, s) in solutions.iter().enumerate() {
+        body.push_str(&group(board, s, (i % columns) * (w + GAP), (i / columns) * (h + GAP)));
+    }
+    return document(columns * (w + GAP) - GAP, rows * (h + GAP) - GAP, &body);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::board::Options;
+    use crate::puzzle::Puzzle;
+
+    fn solutions(n: usize) -> (Board, Vec<Solution>) {
+        let mut board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        let mut res = vec![];
+        board.solutions(&Options { limit: Some(n), ..Options::default() }, |s| {
+            res.push(s.clone());
+            return ControlFlow::Continue(());
+        });
+        return (board, res);
+    }
+
+    #[test]
+    fn outlines() {
+        assert_eq!(outline(&[(0, 0)]), "M0 0 L40 0 L40 40 L0 40 Z");
+        // Only the corners of a straight run.
+        assert_eq!(outline(&[(0, 0), (0, 1), (0, 2)]), "M0 0 L120 0 L120 40 L0 40 Z");
+        assert_eq!(outline(&[(0, 0), (1, 0), (1, 1)]).matches(['M', 'L']).count(), 6);
+        // A ring is two paths.
+        let ring: Vec<_> = (0..3).flat_map(|r| (0..3).map(move |c| (r, c))).filter(|&p| p != (1, 1)).collect();
+        assert_eq!(outline(&ring).matches('Z').count(), 2);
+    }
+
+    #[test]
+    fn render() {
+        let (board, solutions) = solutions(3);
+        let svg = super::render(&board, &solutions[0]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"300\" height=\"300\""));
+        assert_eq!(svg.matches("stroke=\"#222\"").count(), 8);
+        assert!(svg.contains(">JAN</text>") && svg.contains(">1</text>"));
+
+        let sheet = sheet(&board, &solutions, 2);
+        assert!(sheet.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"620\" height=\"620\""));
+        assert_eq!(sheet.matches("<g ").count(), 3);
+        assert_eq!(super::sheet(&board, &[], 2), document(0, 0, ""));
+    }
+}
@@ -0,0 +1,118 @@
+// Rotations and reflections of the board. Two solutions are the same up
+// to symmetry if one of the transforms that map the empty board onto
+// itself maps the one onto the other.
+
+use crate::piece::Cell;
+
+// One of the 8 rotations and reflections of a rectangle: the identity,
+// quarter turns clockwise (1 to 3), mirror images left to right (4) and
+// top to bottom (5), and reflections along the diagonals (6 and 7).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform(usize);
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform(0);
+
+    pub fn all() -> impl Iterator<Item = Transform> {
+        return (0..8).map(Transform);
+    }
+
+    // Height and width of an `h` by `w` grid after the transform.
+    fn size(&self, h: usize, w: usize) -> (usize, usize) {
+        return match self.0 {
+            1 | 3 | 6 | 7 => (w, h),
+            _ => (h, w),
+        };
+    }
+
+    // Where cell (r, c) of an `h` by `w` grid ends up.
+    fn apply(&self, h: usize, w: usize, r: usize, c: usize) -> (usize, usize) {
+        return match self.0 {
+            0 => (r, c),
+            1 => (c, h - 1 - r),
+            2 => (h - 1 - r, w - 1 - c),
+            3 => (w - 1 - c, r),
+            4 => (r, w - 1 - c),
+            5 => (h - 1 - r, c),
+            6 => (c, r),
+            _ => (w - 1 - c, h - 1 - r),
+        };
+    }
+
+    pub fn grid<T: Clone>(&self, grid: &[Vec<T>]) -> Vec<Vec<T>> {
+        let (h, w) = (grid.len(), grid[0].len());
+        let (th, tw) = self.size(h, w);
+        let mut res = vec![vec![grid[0][0].clone(); tw]; th];
+        for (r, row) in grid.iter().enumerate() {
+            for (c, x) in row.iter().enumerate() {
+                let (tr, tc) = self.apply(h, w, r, c);
+                res[tr][tc] = x.clone();
+            }
+        }
+        return res;
+    }
+}
+
+// The transforms that leave `layout` as it is, targets included. Always
+// has the identity first.
+pub fn symmetries(layout: &[Vec<Cell>]) -> Vec<Transform> {
+    return Transform::all().filter(|t| t.grid(layout) == layout).collect();
+}
+
+// Whether `grid` is the smallest of its images under `symmetries`, and how
+// many distinct images it has: the size of its class of solutions.
+pub fn canonical(grid: &[Vec<Cell>], symmetries: &[Transform]) -> (bool, usize) {
+    let mut images: Vec<Vec<Vec<Cell>>> = symmetries.iter().map(|t| t.grid(grid)).collect();
+    let smallest = images.iter().all(|image| grid <= &image[..]);
+    images.sort();
+    images.dedup();
+    return (smallest, images.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Vec<Vec<Cell>> {
+        return rows.iter().map(|r| r.chars().map(|c| match c {
+            '.' => Cell::Empty,
+            '#' => Cell::Blocked,
+            _ => Cell::Piece(c as usize - '0' as usize),
+        }).collect()).collect();
+    }
+
+    #[test]
+    fn transforms() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let image = |t| Transform(t).grid(&grid);
+        assert_eq!(image(0), grid);
+        assert_eq!(image(1), vec![vec![4, 1], vec![5, 2], vec![6, 3]]);
+        assert_eq!(image(2), vec![vec![6, 5, 4], vec![3, 2, 1]]);
+        assert_eq!(image(3), vec![vec![3, 6], vec![2, 5], vec![1, 4]]);
+        assert_eq!(image(4), vec![vec![3, 2, 1], vec![6, 5, 4]]);
+        assert_eq!(image(5), vec![vec![4, 5, 6], vec![1, 2, 3]]);
+        assert_eq!(image(6), vec![vec![1, 4], vec![2, 5], vec![3, 6]]);
+        assert_eq!(image(7), vec![vec![6, 3], vec![5, 2], vec![4, 1]]);
+    }
+
+    #[test]
+    fn board_symmetries() {
+        assert_eq!(symmetries(&cells(&["...", "...", "..."])).len(), 8);
+        assert_eq!(symmetries(&cells(&["...", "..."])), vec![Transform(0), Transform(2), Transform(4), Transform(5)]);
+        assert_eq!(symmetries(&cells(&["#..", "...", "..#"])), vec![Transform(0), Transform(2), Transform(6), Transform(7)]);
+        assert_eq!(symmetries(&cells(&["#..", "..."])), vec![Transform::IDENTITY]);
+    }
+
+    #[test]
+    fn canonical_forms() {
+        let all = symmetries(&cells(&["...", "...", "..."]));
+        // Three straight pieces in rows: the same rows bottom up, and both
+        // as columns.
+        assert_eq!(canonical(&cells(&["000", "111", "222"]), &all), (true, 4));
+        assert_eq!(canonical(&cells(&["222", "111", "000"]), &all), (false, 4));
+        assert_eq!(canonical(&cells(&["012", "012", "012"]), &all), (false, 4));
+        // The same under every transform.
+        assert_eq!(canonical(&cells(&["000", "010", "000"]), &all), (true, 1));
+        assert_eq!(canonical(&cells(&["210", "000", "000"]), &[Transform::IDENTITY]), (true, 1));
+    }
+}
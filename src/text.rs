@@ -0,0 +1,214 @@
+// Terminal drawings of solutions. The emoji style shows each piece by its
+// glyph; the others only use the piece index, so they work whatever the
+// glyphs are and however wide the terminal draws them.
+
+use clap::ValueEnum;
+
+use crate::board::{Board, Solution};
+use crate::date;
+use crate::piece::Cell;
+use crate::svg;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// The piece glyphs, two columns per cell.
+    Emoji,
+    /// A letter per piece, with lines between the pieces.
+    Ascii,
+    /// A coloured block per cell.
+    Ansi,
+}
+
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// Two characters, as wide as the emoji cells.
+pub(crate) fn short(label: &str) -> String {
+    if let Some(m) = (1..=12).find(|&m| date::month_label(m).is_ok_and(|l| label.eq_ignore_ascii_case(&l))) {
+        return format!("{:0>2}", m);
+    }
+    if let Some(w) = (0..7).find(|&w| date::weekday_label(w).is_ok_and(|l| label.eq_ignore_ascii_case(&l))) {
+        return date::WEEKDAYS[w][..2].to_string();
+    }
+    if let Ok(n) = label.parse::<usize>() {
+        return format!("{:0>2}", n);
+    }
+    return format!("{:<2}", label.chars().take(2).collect::<String>());
+}
+
+fn emoji(board: &Board, s: &Solution) -> String {
+    let mut res = String::new();
+    for row in &s.grid {
+        for cell in row {
+            match cell {
+                Cell::Empty => res.push('.'),
+                Cell::Blocked => res.push('⬛'),
+                Cell::Target(label) => res.push_str(&short(label)),
+                Cell::Piece(i) => res.push(board.pieces[*i][0].glyph),
+            }
+        }
+        res.push('\n');
+    }
+    return res;
+}
+
+// Whether a side between two cells separates different regions. Blocked
+// cells count as off the board, and each empty cell is a region of its own.
+fn border(a: Option<&Cell>, b: Option<&Cell>) -> bool {
+    let outside = |x: Option<&Cell>| x.is_none_or(|x| *x == Cell::Blocked);
+    if outside(a) && outside(b) {
+        return false;
+    }
+    return a == Some(&Cell::Empty) || b == Some(&Cell::Empty) || a != b;
+}
+
+// Cells three columns wide, with '+', '-' and '|' on the sides between
+// different regions.
+fn ascii(s: &Solution) -> String {
+    let (h, w) = (s.grid.len() as isize, s.grid[0].len() as isize);
+    let at = |r: isize, c: isize| {
+        if r < 0 || c < 0 || r >= h || c >= w {
+            return None;
+        }
+        return Some(&s.grid[r as usize][c as usize]);
+    };
+
+    let mut res = String::new();
+    for r in 0..=h {
+        let mut line = String::new();
+        for c in 0..=w {
+            let across = border(at(r - 1, c - 1), at(r, c - 1)) || border(at(r - 1, c), at(r, c));
+            let down = border(at(r - 1, c - 1), at(r - 1, c)) || border(at(r, c - 1), at(r, c));
+            line.push(match (across, down) {
+                (true, true) => '+',
+                (true, false) => '-',
+                (false, true) => '|',
+                (false, false) => ' ',
+            });
+            if c < w {
+                line.push_str(if border(at(r - 1, c), at(r, c)) { "---" } else { "   " });
+            }
+        }
+        res.push_str(line.trim_end());
+        res.push('\n');
+        if r == h {
+            break;
+        }
+
+        let mut line = String::new();
+        for c in 0..=w {
+            line.push(if border(at(r, c - 1), at(r, c)) { '|' } else { ' ' });
+            if c < w {
+                match at(r, c) {
+                    Some(Cell::Piece(i)) => {
+                        line.push_str(&format!(" {} ", LETTERS.chars().nth(*i).unwrap_or('?')));
+                    }
+                    Some(Cell::Target(label)) => {
+                        let label: String = label.chars().take(3).collect();
+                        line.push_str(&format!("{:^3}", label.to_uppercase()));
+                    }
+                    _ => line.push_str("   "),
+                }
+            }
+        }
+        res.push_str(line.trim_end());
+        res.push('\n');
+    }
+    return res;
+}
+
+fn background(hex: &str) -> String {
+    let part = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    return format!("\x1b[48;2;{};{};{}m", part(1), part(3), part(5));
+}
+
+fn ansi(board: &Board, s: &Solution) -> String {
+    const RESET: &str = "\x1b[0m";
+    let mut res = String::new();
+    for row in &s.grid {
+        for cell in row {
+            match cell {
+                Cell::Piece(i) => {
+                    let color = svg::color(board.pieces[*i][0].glyph, *i);
+                    res.push_str(&format!("{}  {}", background(color), RESET));
+                }
+                Cell::Target(label) => res.push_str(&short(label)),
+                Cell::Empty => res.push_str(".."),
+                Cell::Blocked => res.push_str(&format!("\x1b[48;5;236m  {}", RESET)),
+            }
+        }
+        res.push('\n');
+    }
+    return res;
+}
+
+pub fn render(board: &Board, s: &Solution, style: Style) -> String {
+    return match style {
+        Style::Emoji => emoji(board, s),
+        Style::Ascii => ascii(s),
+        Style::Ansi => ansi(board, s),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::board::Options;
+    use crate::puzzle::Puzzle;
+
+    fn first() -> (Board, Solution) {
+        let mut board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        let mut res = None;
+        board.solutions(&Options::default(), |s| {
+            res = Some(s.clone());
+            return ControlFlow::Break(());
+        });
+        return (board, res.unwrap());
+    }
+
+    #[test]
+    fn short_labels() {
+        assert_eq!(short("JAN"), "01");
+        assert_eq!(short("dec"), "12");
+        assert_eq!(short("MON"), "Mo");
+        assert_eq!(short("7"), "07");
+        assert_eq!(short("A"), "A ");
+        assert_eq!(short("ABC"), "AB");
+    }
+
+    #[test]
+    fn emoji() {
+        let (board, s) = first();
+        let out = render(&board, &s, Style::Emoji);
+        assert_eq!(out.lines().count(), s.grid.len());
+        assert!(out.lines().next().unwrap().starts_with("01"));
+        assert!(out.lines().nth(2).unwrap().starts_with("01"));
+    }
+
+    #[test]
+    fn ascii() {
+        let (board, s) = first();
+        let out = render(&board, &s, Style::Ascii);
+        // A border line above, below and between each row.
+        assert_eq!(out.lines().count(), 2 * s.grid.len() + 1);
+        assert!(out.lines().nth(1).unwrap().starts_with("|JAN|"));
+        assert!(out.lines().nth(5).unwrap().starts_with("| 1 |"));
+        for i in 0..board.pieces.len() {
+            let letter = LETTERS.chars().nth(i).unwrap();
+            let cells = out.matches(&format!(" {} ", letter)).count();
+            assert_eq!(cells, board.pieces[i][0].size(), "piece {}", letter);
+        }
+    }
+
+    #[test]
+    fn ansi() {
+        let (board, s) = first();
+        let out = render(&board, &s, Style::Ansi);
+        assert_eq!(out.lines().count(), s.grid.len());
+        assert!(out.starts_with("01"));
+        let covered: usize = board.pieces.iter().map(|p| p[0].size()).sum();
+        assert_eq!(out.matches("\x1b[48;2;").count(), covered);
+        assert_eq!(background("#ff8000"), "\x1b[48;2;255;128;0m");
+    }
+}
@@ -0,0 +1,106 @@
+// Checking a filled in board, drawn the way `hint::position` reads it,
+// against the board for the chosen targets.
+
+use crate::board::{Board, Mask};
+use crate::hint;
+use crate::piece::Cell;
+use crate::Error;
+
+// Everything that keeps `text` from being a solution, in reading order and
+// then by piece. Empty if it is one.
+pub fn problems(board: &Board, text: &str) -> Result<Vec<String>, Error> {
+    let rows = hint::rows(board, text)?;
+    let glyphs = hint::glyphs(board)?;
+    let mut res = vec![];
+    let mut masks: Vec<Mask> = vec![0; board.pieces.len()];
+    // Pieces already reported for covering a cell they can't.
+    let mut misplaced = vec![false; board.pieces.len()];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, ch) in row.iter().enumerate() {
+            let at = format!("row {} column {}", r + 1, c + 1);
+            match (&board.layout()[r][c], glyphs.get(ch)) {
+                (Cell::Empty, Some(&i)) => masks[i] |= board.bit(r, c),
+                (Cell::Empty, None) if *ch == '.' || *ch == hint::LABEL => res.push(format!("{}: not covered", at)),
+                (Cell::Empty, None) => res.push(format!("{}: no piece is drawn as {}", at, ch)),
+                (Cell::Target(label), Some(&i)) => {
+                    res.push(format!("{}: piece {} covers the target {}", at, board.pieces[i][0].name, label));
+                    misplaced[i] = true;
+                }
+                (_, Some(&i)) => {
+                    res.push(format!("{}: piece {} covers a blocked cell", at, board.pieces[i][0].name));
+                    misplaced[i] = true;
+                }
+                (_, None) => {}
+            }
+        }
+    }
+
+    for (i, &mask) in masks.iter().enumerate() {
+        let piece = &board.pieces[i][0];
+        let size = mask.count_ones() as usize;
+        if misplaced[i] {
+            continue;
+        } else if mask == 0 {
+            res.push(format!("piece {} is missing", piece.name));
+        } else if size != piece.size() {
+            res.push(format!("piece {} covers {} cells, it has {}", piece.name, size, piece.size()));
+        } else if board.placement(i, mask).is_none() {
+            res.push(format!("piece {} isn't in any of its allowed orientations", piece.name));
+        }
+    }
+    return Ok(res);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::board::Options;
+    use crate::puzzle::Puzzle;
+    use crate::text::{self, Style};
+
+    fn board(text: &str, targets: &[&str]) -> Board {
+        return Board::new(&Puzzle::parse("test", text).unwrap(), targets).unwrap();
+    }
+
+    #[test]
+    fn solutions() {
+        let b = board("[board]\n....\n....\n[pieces]\naaa\n\nbbb\n[labels]\nX 0 3\nY 1 0\n", &["X", "Y"]);
+        assert_eq!(problems(&b, "aaa.\n.bbb\n"), Ok(vec![]));
+        assert_eq!(problems(&b, "aaaX\nYbbb\n"), Ok(vec![]));
+        assert_eq!(problems(&b, "aa..\n.bbb\n"),
+                   Ok(vec!["row 1 column 3: not covered".to_string(), "piece a covers 2 cells, it has 3".to_string()]));
+        assert_eq!(problems(&b, "aaaa\n.bbb\n"), Ok(vec!["row 1 column 4: piece a covers the target X".to_string()]));
+        assert_eq!(problems(&b, "....\n.bbb\n").unwrap().last().unwrap(), "piece a is missing");
+        assert_eq!(problems(&b, "aaa.\n.bxb\n").unwrap()[0], "row 2 column 3: no piece is drawn as x");
+        assert!(problems(&b, "aaa.\n").is_err());
+    }
+
+    #[test]
+    fn cells_and_orientations() {
+        let b = board("[board]\n..#\n[pieces]\naa\n", &[]);
+        assert_eq!(problems(&b, "aaa\n"), Ok(vec!["row 1 column 3: piece a covers a blocked cell".to_string()]));
+        let b = board("[board]\n..\n..\n[pieces]\npiece a fixed\naa\n\nbb\n", &[]);
+        assert_eq!(problems(&b, "aa\nbb\n"), Ok(vec![]));
+        assert_eq!(problems(&b, "ab\nab\n"), Ok(vec!["piece a isn't in any of its allowed orientations".to_string()]));
+    }
+
+    #[test]
+    fn emoji_output() {
+        let board = Board::new(&Puzzle::month_day(), &["JAN", "1"]).unwrap();
+        let mut drawn = String::new();
+        board.clone().solutions(&Options::default(), |s| {
+            drawn = text::render(&board, s, Style::Emoji);
+            return ControlFlow::Break(());
+        });
+        assert_eq!(problems(&board, &drawn), Ok(vec![]));
+
+        // Drawn for another day, the 1 is left uncovered and the 2 covered.
+        let other = Board::new(&Puzzle::month_day(), &["JAN", "2"]).unwrap();
+        let found = problems(&other, &drawn).unwrap();
+        assert_eq!(found[0], "row 3 column 1: not covered");
+        assert!(found[1].starts_with("row 3 column 2: piece ") && found[1].ends_with(" covers the target 2"), "{:?}", found);
+        assert_eq!(found.len(), 2);
+    }
+}